@@ -0,0 +1,183 @@
+//! Editing and drawing a book-length manuscript, to catch slowdowns in the
+//! paths that run on every keystroke. Run with `cargo bench`.
+
+use clack_rs::app::App;
+use clack_rs::config::UserPreferences;
+use clack_rs::ui;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use ratatui::{backend::TestBackend, Terminal};
+use std::hint::black_box;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Size of the generated manuscript: a little over 3 MB of text
+const CHAPTERS: usize = 60;
+const PARAGRAPHS_PER_CHAPTER: usize = 100;
+
+/// Repeated, a word at a time, to make up the manuscript
+const PROSE: &str = "The lighthouse keeper had *never* seen a storm like it, and when \
+    morning came **nothing** was where she left it. Gulls wheeled over the broken pier \
+    while the tide went out further than anyone could remember; boats lay on their \
+    sides in the mud like tired dogs.";
+
+/// Prose hard-wrapped at 60 columns (inside the default margin stop), with
+/// chapter headings and the odd bit of emphasis
+fn manuscript() -> String {
+    let mut words = PROSE.split_whitespace().cycle();
+    let mut text = String::new();
+    for chapter in 1..=CHAPTERS {
+        text.push_str(&format!("# Chapter {chapter}\n\n"));
+        for _ in 0..PARAGRAPHS_PER_CHAPTER {
+            let mut line_len = 0;
+            for _ in 0..90 {
+                let next = words.next().unwrap();
+                if line_len + next.len() + 1 > 60 {
+                    text.push('\n');
+                    line_len = 0;
+                } else if line_len > 0 {
+                    text.push(' ');
+                    line_len += 1;
+                }
+                text.push_str(next);
+                line_len += next.len();
+            }
+            text.push_str("\n\n");
+        }
+    }
+    text
+}
+
+/// An app with the manuscript loaded from disk and sound off
+fn load_app() -> App {
+    let path = manuscript_path();
+    let prefs = UserPreferences {
+        sound_enabled: false,
+        ..UserPreferences::default()
+    };
+    let mut app = App::new(prefs);
+    app.load_file(path).expect("load manuscript");
+    app
+}
+
+/// Where the generated manuscript is written. It's rewritten on the first
+/// call of each run, so a file left by a run with a different PROSE or
+/// size is never measured.
+fn manuscript_path() -> PathBuf {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let path = std::env::temp_dir().join("clack_bench_manuscript.md");
+        std::fs::write(&path, manuscript()).expect("write manuscript");
+        path
+    })
+    .clone()
+}
+
+/// Cursor positions to edit at: the start, middle and end of the document
+fn positions(app: &App) -> [(&'static str, usize); 3] {
+    let len = app.doc.content.len_chars();
+    // Back off the final newline so the middle and end land inside a line
+    [("start", 0), ("middle", len / 2), ("end", len - 2)]
+}
+
+fn bench_editing(c: &mut Criterion) {
+    let mut app = load_app();
+    let mut group = c.benchmark_group("edit");
+
+    for (name, idx) in positions(&app) {
+        group.bench_function(format!("insert_char/{name}"), |b| {
+            b.iter_custom(|iters| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    app.doc.cursor = idx;
+                    let start = Instant::now();
+                    app.insert_char(black_box('x'));
+                    total += start.elapsed();
+                    app.delete_char();
+                }
+                total
+            })
+        });
+        group.bench_function(format!("delete_char/{name}"), |b| {
+            b.iter_custom(|iters| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    app.doc.cursor = idx;
+                    app.insert_char('x');
+                    let start = Instant::now();
+                    app.delete_char();
+                    total += start.elapsed();
+                }
+                total
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_word_count(c: &mut Criterion) {
+    let mut app = load_app();
+    let middle = app.doc.content.len_chars() / 2;
+    c.bench_function("word_count/after_edit", |b| {
+        b.iter_custom(|iters| {
+            let mut total = Duration::ZERO;
+            for _ in 0..iters {
+                // An edit throws the cached count away
+                app.doc.cursor = middle;
+                app.insert_char('x');
+                app.delete_char();
+                let start = Instant::now();
+                black_box(app.get_word_count());
+                total += start.elapsed();
+            }
+            total
+        })
+    });
+}
+
+fn bench_draw(c: &mut Criterion) {
+    let mut app = load_app();
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).expect("test terminal");
+    let mut group = c.benchmark_group("draw");
+
+    for (name, idx) in positions(&app) {
+        app.doc.cursor = idx;
+        group.bench_function(format!("unchanged/{name}"), |b| {
+            b.iter(|| {
+                terminal.draw(|f| ui::draw(f, &mut app)).unwrap();
+            })
+        });
+        group.bench_function(format!("after_keystroke/{name}"), |b| {
+            b.iter_custom(|iters| {
+                let mut total = Duration::ZERO;
+                for i in 0..iters {
+                    // Type a letter, then take it back on the next frame
+                    if i % 2 == 0 {
+                        app.doc.cursor = idx;
+                        app.insert_char('x');
+                    } else {
+                        app.delete_char();
+                    }
+                    let start = Instant::now();
+                    terminal.draw(|f| ui::draw(f, &mut app)).unwrap();
+                    total += start.elapsed();
+                }
+                total
+            })
+        });
+    }
+
+    // The first frame after opening, with nothing laid out yet
+    group.sample_size(10);
+    group.bench_function("first_frame", |b| {
+        b.iter_batched(
+            load_app,
+            |mut app| terminal.draw(|f| ui::draw(f, &mut app)).map(|_| ()),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_editing, bench_word_count, bench_draw);
+criterion_main!(benches);
@@ -0,0 +1,173 @@
+use crate::history::{Edit, History};
+use ropey::Rope;
+
+/// Text being edited, with its cursor and undo history. Every change made
+/// through these methods is recorded for undo; each method takes an
+/// `applied` callback that sees the changes as they land in the rope, so
+/// caches built over the text (e.g. line layout) can follow along.
+#[derive(Default)]
+pub struct Document {
+    pub content: Rope,
+    /// Char index of the cursor
+    pub cursor: usize,
+    pub history: History,
+}
+
+impl Document {
+    /// A document holding `content`, with the cursor at the start and
+    /// nothing to undo
+    pub fn new(content: Rope) -> Self {
+        Self {
+            content,
+            cursor: 0,
+            history: History::new(),
+        }
+    }
+
+    /// Insert text at the cursor and move the cursor past it
+    pub fn insert(&mut self, text: &str, mut applied: impl FnMut(&Rope, &Edit)) {
+        let idx = self.cursor;
+        self.content.insert(idx, text);
+        self.cursor = idx + text.chars().count();
+        let edit = Edit::Insert {
+            idx,
+            text: text.to_string(),
+        };
+        applied(&self.content, &edit);
+        self.history.record(edit, idx, self.cursor);
+    }
+
+    /// Remove the chars in `start..end` and put the cursor at `cursor_after`
+    pub fn remove(
+        &mut self,
+        start: usize,
+        end: usize,
+        cursor_after: usize,
+        mut applied: impl FnMut(&Rope, &Edit),
+    ) {
+        let text = self.content.slice(start..end).to_string();
+        let cursor_before = self.cursor;
+        self.content.remove(start..end);
+        self.cursor = cursor_after;
+        let edit = Edit::Delete { idx: start, text };
+        applied(&self.content, &edit);
+        self.history.record(edit, cursor_before, cursor_after);
+    }
+
+    /// Replace each `(start, end, text)` as a single undo step, leaving the
+    /// cursor after the last replacement. Ranges must be in document order
+    /// and must not overlap.
+    pub fn replace_ranges(
+        &mut self,
+        replacements: &[(usize, usize, String)],
+        mut applied: impl FnMut(&Rope, &Edit),
+    ) {
+        let Some((last_start, _, last_text)) = replacements.last() else {
+            return;
+        };
+        let cursor_before = self.cursor;
+
+        // Shift of the last range caused by replacing the ones before it
+        let shift: isize = replacements[..replacements.len() - 1]
+            .iter()
+            .map(|(start, end, text)| text.chars().count() as isize - (end - start) as isize)
+            .sum();
+        let cursor_after = (*last_start as isize + shift) as usize + last_text.chars().count();
+
+        // Work backwards so earlier ranges keep their indices
+        let mut edits = Vec::new();
+        for (start, end, text) in replacements.iter().rev() {
+            let removed = self.content.slice(*start..*end).to_string();
+            self.content.remove(*start..*end);
+            let delete = Edit::Delete {
+                idx: *start,
+                text: removed,
+            };
+            applied(&self.content, &delete);
+            edits.push(delete);
+            if !text.is_empty() {
+                self.content.insert(*start, text);
+                let insert = Edit::Insert {
+                    idx: *start,
+                    text: text.clone(),
+                };
+                applied(&self.content, &insert);
+                edits.push(insert);
+            }
+        }
+
+        self.cursor = cursor_after;
+        self.history
+            .record_group(edits, cursor_before, cursor_after);
+    }
+
+    /// Revert the most recent step. Returns false if there was nothing to undo.
+    pub fn undo(&mut self, applied: impl FnMut(&Rope, &Edit)) -> bool {
+        match self.history.undo(&mut self.content, applied) {
+            Some(cursor) => {
+                self.cursor = cursor.min(self.content.len_chars());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Re-apply the most recently undone step. Returns false if there was
+    /// nothing to redo.
+    pub fn redo(&mut self, applied: impl FnMut(&Rope, &Edit)) -> bool {
+        match self.history.redo(&mut self.content, applied) {
+            Some(cursor) => {
+                self.cursor = cursor.min(self.content.len_chars());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignore(_: &Rope, _: &Edit) {}
+
+    #[test]
+    fn test_edits_undo_and_redo_with_the_cursor() {
+        let mut doc = Document::new(Rope::from_str("hello"));
+        doc.cursor = 5;
+        doc.insert(" world", ignore);
+        assert_eq!(doc.content.to_string(), "hello world");
+        assert_eq!(doc.cursor, 11);
+
+        doc.history.seal();
+        doc.remove(0, 6, 0, ignore);
+        assert_eq!(doc.content.to_string(), "world");
+
+        assert!(doc.undo(ignore));
+        assert_eq!(doc.content.to_string(), "hello world");
+        assert_eq!(doc.cursor, 11);
+        assert!(doc.undo(ignore));
+        assert_eq!(doc.content.to_string(), "hello");
+        assert_eq!(doc.cursor, 5);
+        assert!(!doc.undo(ignore));
+
+        assert!(doc.redo(ignore));
+        assert_eq!(doc.content.to_string(), "hello world");
+    }
+
+    #[test]
+    fn test_replace_ranges_is_one_step_and_reports_each_change() {
+        let mut doc = Document::new(Rope::from_str("a cat and a cat"));
+        let mut seen = Vec::new();
+        doc.replace_ranges(
+            &[(2, 5, "dog".to_string()), (12, 15, "bird".to_string())],
+            |_, edit| seen.push(edit.clone()),
+        );
+        assert_eq!(doc.content.to_string(), "a dog and a bird");
+        assert_eq!(doc.cursor, 16);
+        assert_eq!(seen.len(), 4);
+
+        assert!(doc.undo(ignore));
+        assert_eq!(doc.content.to_string(), "a cat and a cat");
+    }
+}
@@ -0,0 +1,215 @@
+use ropey::Rope;
+use std::time::{Duration, Instant};
+
+/// Keystrokes further apart than this start a new undo step
+const GROUP_PAUSE: Duration = Duration::from_millis(1000);
+
+/// A single primitive change to the document
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    Insert { idx: usize, text: String },
+    Delete { idx: usize, text: String },
+}
+
+impl Edit {
+    fn apply(&self, rope: &mut Rope) {
+        match self {
+            Edit::Insert { idx, text } => rope.insert(*idx, text),
+            Edit::Delete { idx, text } => rope.remove(*idx..*idx + text.chars().count()),
+        }
+    }
+
+    fn revert(&self, rope: &mut Rope) {
+        match self {
+            Edit::Insert { idx, text } => rope.remove(*idx..*idx + text.chars().count()),
+            Edit::Delete { idx, text } => rope.insert(*idx, text),
+        }
+    }
+
+    /// The edit that takes it back
+    fn inverse(&self) -> Edit {
+        match self {
+            Edit::Insert { idx, text } => Edit::Delete {
+                idx: *idx,
+                text: text.clone(),
+            },
+            Edit::Delete { idx, text } => Edit::Insert {
+                idx: *idx,
+                text: text.clone(),
+            },
+        }
+    }
+
+    /// Try to fold `next` into this edit so a run of keystrokes undoes as one word
+    fn merge(&mut self, next: &Edit) -> bool {
+        match (self, next) {
+            (
+                Edit::Insert { idx, text },
+                Edit::Insert {
+                    idx: next_idx,
+                    text: next_text,
+                },
+            ) => {
+                if *next_idx != *idx + text.chars().count() {
+                    return false;
+                }
+                // Break the group where a new word starts after whitespace
+                let ends_in_space = text.chars().last().is_some_and(char::is_whitespace);
+                let starts_word = next_text.chars().next().is_some_and(|c| !c.is_whitespace());
+                if ends_in_space && starts_word {
+                    return false;
+                }
+                text.push_str(next_text);
+                true
+            }
+            (
+                Edit::Delete { idx, text },
+                Edit::Delete {
+                    idx: next_idx,
+                    text: next_text,
+                },
+            ) => {
+                if *next_idx + next_text.chars().count() == *idx {
+                    // Backspace: the deleted run grows to the left
+                    text.insert_str(0, next_text);
+                    *idx = *next_idx;
+                    true
+                } else if *next_idx == *idx {
+                    // Forward delete: the deleted run grows to the right
+                    text.push_str(next_text);
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A group of edits that is undone and redone as one step
+struct Transaction {
+    id: usize,
+    edits: Vec<Edit>,
+    cursor_before: usize,
+    cursor_after: usize,
+    last_edit_at: Instant,
+    sealed: bool,
+}
+
+/// Undo/redo stacks for a document
+pub struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    next_id: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Record an edit that has already been applied to the document
+    pub fn record(&mut self, edit: Edit, cursor_before: usize, cursor_after: usize) {
+        self.redo_stack.clear();
+        let now = Instant::now();
+
+        if let Some(top) = self.undo_stack.last_mut() {
+            if !top.sealed && now.duration_since(top.last_edit_at) < GROUP_PAUSE {
+                if let Some(last) = top.edits.last_mut() {
+                    if last.merge(&edit) {
+                        top.cursor_after = cursor_after;
+                        top.last_edit_at = now;
+                        return;
+                    }
+                }
+            }
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.undo_stack.push(Transaction {
+            id,
+            edits: vec![edit],
+            cursor_before,
+            cursor_after,
+            last_edit_at: now,
+            sealed: false,
+        });
+    }
+
+    /// Record several edits that must be undone together (e.g. replacing a selection)
+    pub fn record_group(&mut self, edits: Vec<Edit>, cursor_before: usize, cursor_after: usize) {
+        if edits.is_empty() {
+            return;
+        }
+        self.redo_stack.clear();
+        let id = self.next_id;
+        self.next_id += 1;
+        self.undo_stack.push(Transaction {
+            id,
+            edits,
+            cursor_before,
+            cursor_after,
+            last_edit_at: Instant::now(),
+            sealed: true,
+        });
+    }
+
+    /// Stop further edits from being merged into the most recent step
+    pub fn seal(&mut self) {
+        if let Some(top) = self.undo_stack.last_mut() {
+            top.sealed = true;
+        }
+    }
+
+    /// Identifier of the current document state, used to detect a return to the saved state
+    pub fn revision(&self) -> usize {
+        self.undo_stack.last().map_or(0, |t| t.id)
+    }
+
+    /// Revert the most recent step, returning the cursor position to restore.
+    /// `applied` sees each change as it is made to the rope.
+    pub fn undo(
+        &mut self,
+        rope: &mut Rope,
+        mut applied: impl FnMut(&Rope, &Edit),
+    ) -> Option<usize> {
+        let mut transaction = self.undo_stack.pop()?;
+        for edit in transaction.edits.iter().rev() {
+            edit.revert(rope);
+            applied(rope, &edit.inverse());
+        }
+        transaction.sealed = true;
+        let cursor = transaction.cursor_before;
+        self.redo_stack.push(transaction);
+        Some(cursor)
+    }
+
+    /// Re-apply the most recently undone step, returning the cursor position
+    /// to restore. `applied` sees each change as it is made to the rope.
+    pub fn redo(
+        &mut self,
+        rope: &mut Rope,
+        mut applied: impl FnMut(&Rope, &Edit),
+    ) -> Option<usize> {
+        let transaction = self.redo_stack.pop()?;
+        for edit in &transaction.edits {
+            edit.apply(rope);
+            applied(rope, edit);
+        }
+        let cursor = transaction.cursor_after;
+        self.undo_stack.push(transaction);
+        Some(cursor)
+    }
+}
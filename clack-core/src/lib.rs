@@ -0,0 +1,12 @@
+//! The editing engine behind `clack`, with no terminal or audio attached:
+//! the document and its undo history, cursor motion, pages, word counts and
+//! Markdown spans. The terminal front end builds on it, and it can be
+//! embedded and tested on its own.
+
+pub mod document;
+pub mod history;
+pub mod markdown;
+pub mod motion;
+pub mod page;
+pub mod words;
+pub mod wrap;
@@ -0,0 +1,726 @@
+/// What the lines before this one leave open. Only fenced code blocks span
+/// lines; everything else is decided line by line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BlockState {
+    fence: Option<Fence>,
+}
+
+/// The opening of a fenced code block: a run of backticks or tildes, at
+/// least three long
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Fence {
+    ch: char,
+    len: usize,
+}
+
+/// How a run of characters should look
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Format {
+    /// Markdown syntax (`**`, `#`, `>`, link targets...), drawn dimmed
+    pub markup: bool,
+    /// List bullet, number or task box
+    pub bullet: bool,
+    /// Horizontal rule
+    pub rule: bool,
+    /// Inline code or the inside of a fenced code block
+    pub code: bool,
+    pub bold: bool,
+    pub italic: bool,
+    pub strike: bool,
+    /// Link text
+    pub link: bool,
+    /// Heading level 1-6, or 0 outside headings
+    pub heading: u8,
+    /// Inside a block quote
+    pub quote: bool,
+    /// Text of a checked task
+    pub done: bool,
+}
+
+/// A run of characters from the source line with one format. The segments of
+/// a line put back together give the line exactly, markup included.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub text: String,
+    pub format: Format,
+}
+
+/// Split a line into formatted segments, updating `state` for the next line
+pub fn parse_segments(line: &str, state: &mut BlockState) -> Vec<Segment> {
+    let trimmed = line.trim_end();
+    let chars: Vec<char> = trimmed.chars().collect();
+    let trailing: Vec<char> = line[trimmed.len()..].chars().collect();
+    let mut out = Vec::new();
+    // Trailing whitespace is parsed as nothing, but still gets a segment so
+    // it can be selected and highlighted like the rest of the line
+    let mut trailing_format = Format::default();
+
+    if let Some(fence) = state.fence {
+        let format = if closes_fence(&chars, fence) {
+            state.fence = None;
+            Format {
+                markup: true,
+                ..Format::default()
+            }
+        } else {
+            trailing_format.code = true;
+            trailing_format
+        };
+        push(&mut out, &chars, format);
+    } else if let Some(fence) = opens_fence(&chars) {
+        state.fence = Some(fence);
+        let markup = Format {
+            markup: true,
+            ..Format::default()
+        };
+        push(&mut out, &chars, markup);
+    } else {
+        parse_block(&chars, Format::default(), &mut out);
+    }
+    push(&mut out, &trailing, trailing_format);
+    out
+}
+
+/// The characters to draw for a line, each with its format and its char
+/// offset in the source line. With `hide_markup`, syntax characters are left
+/// out, so offsets skip over them.
+pub fn visible_chars(segments: &[Segment], hide_markup: bool) -> Vec<(char, Format, usize)> {
+    let mut out = Vec::new();
+    let mut offset = 0;
+    for segment in segments {
+        let hidden = hide_markup && segment.format.markup;
+        for ch in segment.text.chars() {
+            if !hidden {
+                out.push((ch, segment.format, offset));
+            }
+            offset += 1;
+        }
+    }
+    out
+}
+
+/// Append `chars` with `format`, merging into the previous segment when the
+/// format is the same
+fn push(out: &mut Vec<Segment>, chars: &[char], format: Format) {
+    if chars.is_empty() {
+        return;
+    }
+    match out.last_mut() {
+        Some(last) if last.format == format => last.text.extend(chars),
+        _ => out.push(Segment {
+            text: chars.iter().collect(),
+            format,
+        }),
+    }
+}
+
+fn leading_spaces(chars: &[char]) -> usize {
+    chars.iter().take_while(|&&c| c == ' ').count()
+}
+
+/// A run of three or more backticks or tildes, indented at most three
+/// spaces. Backtick fences can't have backticks in their info string.
+fn opens_fence(chars: &[char]) -> Option<Fence> {
+    let indent = leading_spaces(chars);
+    let ch = *chars.get(indent)?;
+    if indent > 3 || !matches!(ch, '`' | '~') {
+        return None;
+    }
+    let len = chars[indent..].iter().take_while(|&&c| c == ch).count();
+    let info = &chars[indent + len..];
+    (len >= 3 && !(ch == '`' && info.contains(&'`'))).then_some(Fence { ch, len })
+}
+
+/// A run of the opening fence character at least as long, and nothing else
+fn closes_fence(chars: &[char], fence: Fence) -> bool {
+    let indent = leading_spaces(chars);
+    let rest = &chars[indent..];
+    indent <= 3 && rest.len() >= fence.len && rest.iter().all(|&c| c == fence.ch)
+}
+
+/// Three or more `-`, `*` or `_`, all the same, optionally spaced out
+fn is_rule(chars: &[char]) -> bool {
+    let Some(&ch) = chars.iter().find(|&&c| c != ' ') else {
+        return false;
+    };
+    matches!(ch, '-' | '*' | '_')
+        && chars.iter().all(|&c| c == ch || c == ' ')
+        && chars.iter().filter(|&&c| c == ch).count() >= 3
+}
+
+/// Level of an ATX heading: one to six `#` followed by a space or the end
+fn heading_level(chars: &[char]) -> Option<usize> {
+    let level = chars.iter().take_while(|&&c| c == '#').count();
+    let followed_by_space = matches!(chars.get(level), None | Some(' '));
+    ((1..=6).contains(&level) && followed_by_space).then_some(level)
+}
+
+/// End of a list marker (`-`, `*`, `+`, `1.` or `1)`) and the space after it
+fn list_marker(chars: &[char], indent: usize) -> Option<usize> {
+    let rest = &chars[indent..];
+    let marker_len = match rest.first()? {
+        '-' | '*' | '+' => 1,
+        c if c.is_ascii_digit() => {
+            let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits > 9 || !matches!(rest.get(digits), Some('.' | ')')) {
+                return None;
+            }
+            digits + 1
+        }
+        _ => return None,
+    };
+    match rest.get(marker_len) {
+        None => Some(indent + marker_len),
+        Some(' ') => Some(indent + marker_len + 1),
+        Some(_) => None,
+    }
+}
+
+/// Block structure at the start of a line (rules, headings, quotes, lists),
+/// then inline formatting for the rest
+fn parse_block(chars: &[char], base: Format, out: &mut Vec<Segment>) {
+    let indent = leading_spaces(chars);
+    let markup = Format {
+        markup: true,
+        ..base
+    };
+
+    if indent <= 3 && is_rule(chars) {
+        push(out, chars, Format { rule: true, ..base });
+        return;
+    }
+
+    if indent <= 3 {
+        if let Some(level) = heading_level(&chars[indent..]) {
+            let start = (indent + level + 1).min(chars.len());
+            push(out, &chars[..start], markup);
+            let body = &chars[start..];
+            let end = heading_content_end(body);
+            let heading = Format {
+                heading: level as u8,
+                ..base
+            };
+            parse_inline(&body[..end], heading, out);
+            push(out, &body[end..], markup);
+            return;
+        }
+
+        if chars.get(indent) == Some(&'>') {
+            let mut end = indent + 1;
+            if chars.get(end) == Some(&' ') {
+                end += 1;
+            }
+            push(out, &chars[..end], markup);
+            let quoted = Format {
+                quote: true,
+                ..base
+            };
+            parse_block(&chars[end..], quoted, out);
+            return;
+        }
+    }
+
+    if let Some(end) = list_marker(chars, indent) {
+        push(out, &chars[..indent], base);
+        push(
+            out,
+            &chars[indent..end],
+            Format {
+                bullet: true,
+                ..base
+            },
+        );
+        let rest = &chars[end..];
+        let (task_len, checked) = match rest {
+            ['[', ' ', ']'] | ['[', ' ', ']', ' ', ..] => (rest.len().min(4), false),
+            ['[', 'x' | 'X', ']'] | ['[', 'x' | 'X', ']', ' ', ..] => (rest.len().min(4), true),
+            _ => (0, false),
+        };
+        push(
+            out,
+            &rest[..task_len],
+            Format {
+                bullet: true,
+                ..base
+            },
+        );
+        let item = Format {
+            done: checked,
+            ..base
+        };
+        parse_inline(&rest[task_len..], item, out);
+        return;
+    }
+
+    parse_inline(chars, base, out);
+}
+
+/// Where a heading's text ends, before any closing run of `#` (which must
+/// follow a space) and the spaces in front of it
+fn heading_content_end(body: &[char]) -> usize {
+    let hashes = body.iter().rev().take_while(|&&c| c == '#').count();
+    if hashes == 0 {
+        return body.len();
+    }
+    let before = body.len() - hashes;
+    if before == 0 {
+        return 0;
+    }
+    if body[before - 1] != ' ' {
+        return body.len();
+    }
+    body[..before]
+        .iter()
+        .rposition(|&c| c != ' ')
+        .map_or(0, |i| i + 1)
+}
+
+/// A run of `*`, `_` or `~~` that may open or close emphasis. Matches use up
+/// the characters nearest the enclosed text, so an opener shrinks from its end
+/// and a closer from its start; whatever is left over stays literal text.
+struct Delimiter {
+    ch: char,
+    start: usize,
+    end: usize,
+    /// Length of the whole run, for the rule of three
+    run: usize,
+    can_open: bool,
+    can_close: bool,
+}
+
+impl Delimiter {
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// Inline formatting: code spans, emphasis (`*`, `_`, doubled for strong),
+/// `~~strikethrough~~`, `[links](url)` and backslash escapes
+fn parse_inline(chars: &[char], format: Format, out: &mut Vec<Segment>) {
+    for (ch, format) in chars.iter().zip(inline_formats(chars, format)) {
+        push(out, std::slice::from_ref(ch), format);
+    }
+}
+
+/// The format of each character of `chars`. Escapes, code spans and links
+/// are found first, left to right, and hide their contents from emphasis;
+/// the delimiter runs outside them are then paired up as CommonMark does.
+fn inline_formats(chars: &[char], format: Format) -> Vec<Format> {
+    let markup = Format {
+        markup: true,
+        ..format
+    };
+    let mut formats = vec![format; chars.len()];
+    let mut delimiters = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let rest = &chars[i..];
+        match rest[0] {
+            '\\' if rest.get(1).is_some_and(|c| c.is_ascii_punctuation()) => {
+                formats[i] = markup;
+                i += 2;
+            }
+            '`' => {
+                let run = rest.iter().take_while(|&&c| c == '`').count();
+                if let Some((_, close)) = code_span(rest) {
+                    let code = Format {
+                        code: true,
+                        ..format
+                    };
+                    formats[i..i + run].fill(markup);
+                    formats[i + run..i + close].fill(code);
+                    formats[i + close..i + close + run].fill(markup);
+                    i += close + run;
+                } else {
+                    // An unmatched run is literal, backticks and all
+                    i += run;
+                }
+            }
+            '[' => match link(rest) {
+                Some((text_end, end)) => {
+                    let text = Format {
+                        link: true,
+                        ..format
+                    };
+                    formats[i] = markup;
+                    formats[i + 1..i + text_end]
+                        .copy_from_slice(&inline_formats(&rest[1..text_end], text));
+                    formats[i + text_end..i + end].fill(markup);
+                    i += end;
+                }
+                None => i += 1,
+            },
+            ch @ ('*' | '_' | '~') => {
+                let run = rest.iter().take_while(|&&c| c == ch).count();
+                // Strikethrough takes exactly two tildes
+                if ch != '~' || run == 2 {
+                    delimiters.push(delimiter_run(chars, i, run));
+                }
+                i += run;
+            }
+            _ => i += 1,
+        }
+    }
+
+    match_emphasis(&mut delimiters, &mut formats);
+    formats
+}
+
+/// Classify the run of `len` delimiter characters at `start` by what is on
+/// either side of it. The start and end of the text count as whitespace.
+fn delimiter_run(chars: &[char], start: usize, len: usize) -> Delimiter {
+    let ch = chars[start];
+    let before = start.checked_sub(1).map(|j| chars[j]);
+    let after = chars.get(start + len).copied();
+    let space_before = before.is_none_or(char::is_whitespace);
+    let space_after = after.is_none_or(char::is_whitespace);
+    let punct_before = before.is_some_and(is_punctuation);
+    let punct_after = after.is_some_and(is_punctuation);
+
+    let left_flanking = !space_after && (!punct_after || space_before || punct_before);
+    let right_flanking = !space_before && (!punct_before || space_after || punct_after);
+    // `_` inside a word (snake_case) neither opens nor closes
+    let (can_open, can_close) = if ch == '_' {
+        (
+            left_flanking && (!right_flanking || punct_before),
+            right_flanking && (!left_flanking || punct_after),
+        )
+    } else {
+        (left_flanking, right_flanking)
+    };
+
+    Delimiter {
+        ch,
+        start,
+        end: start + len,
+        run: len,
+        can_open,
+        can_close,
+    }
+}
+
+/// Punctuation or a symbol, for the flanking rules. Without Unicode category
+/// tables, anything that isn't a letter, digit, space or control counts.
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || !(c.is_alphanumeric() || c.is_whitespace() || c.is_control())
+}
+
+/// Pair closers with the nearest earlier opener of the same kind, innermost
+/// first, marking the delimiters as markup and formatting what they enclose.
+/// Unpaired delimiters between a matched pair can't pair across it later.
+fn match_emphasis(delimiters: &mut [Delimiter], formats: &mut [Format]) {
+    for closer in 0..delimiters.len() {
+        while delimiters[closer].can_close && delimiters[closer].len() > 0 {
+            let c = &delimiters[closer];
+            let Some(opener) = (0..closer).rev().find(|&o| {
+                let o = &delimiters[o];
+                // Rule of three: a run that could both open and close only
+                // pairs with another whose length doesn't add up to a
+                // multiple of three, unless both lengths are
+                let rule_of_three = (o.can_close || c.can_open)
+                    && (o.run + c.run).is_multiple_of(3)
+                    && !(o.run.is_multiple_of(3) && c.run.is_multiple_of(3));
+                o.ch == c.ch && o.can_open && o.len() > 0 && !rule_of_three
+            }) else {
+                break;
+            };
+
+            let ch = c.ch;
+            let used = if delimiters[opener].len() >= 2 && delimiters[closer].len() >= 2 {
+                2
+            } else {
+                1
+            };
+            let (inner_start, inner_end) = (delimiters[opener].end, delimiters[closer].start);
+            for f in &mut formats[inner_start - used..inner_start] {
+                f.markup = true;
+            }
+            for f in &mut formats[inner_end..inner_end + used] {
+                f.markup = true;
+            }
+            for f in &mut formats[inner_start..inner_end] {
+                match (ch, used) {
+                    ('~', _) => f.strike = true,
+                    (_, 2) => f.bold = true,
+                    _ => f.italic = true,
+                }
+            }
+
+            for d in &mut delimiters[opener + 1..closer] {
+                d.end = d.start;
+            }
+            delimiters[opener].end -= used;
+            delimiters[closer].start += used;
+        }
+    }
+}
+
+/// A code span at the start of `chars`: the length of its opening backtick
+/// run and where the matching closing run (of the same length) starts
+fn code_span(chars: &[char]) -> Option<(usize, usize)> {
+    let run = chars.iter().take_while(|&&c| c == '`').count();
+    let mut i = run;
+    while i < chars.len() {
+        if chars[i] == '`' {
+            let close = chars[i..].iter().take_while(|&&c| c == '`').count();
+            if close == run {
+                return Some((run, i));
+            }
+            i += close;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// `[text](url)` at the start of `chars`: where the text ends and where the
+/// whole link ends
+fn link(chars: &[char]) -> Option<(usize, usize)> {
+    let text_end = chars.iter().position(|&c| c == ']')?;
+    if chars.get(text_end + 1) != Some(&'(') {
+        return None;
+    }
+    let close = chars[text_end..].iter().position(|&c| c == ')')? + text_end;
+    Some((text_end, close + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Vec<Segment> {
+        parse_segments(line, &mut BlockState::default())
+    }
+
+    /// Text of each segment with a tag for its most notable format
+    fn tagged(segments: &[Segment]) -> Vec<(&str, &str)> {
+        segments
+            .iter()
+            .map(|s| {
+                let f = s.format;
+                let tag = if f.markup {
+                    "markup"
+                } else if f.bullet {
+                    "bullet"
+                } else if f.rule {
+                    "rule"
+                } else if f.code {
+                    "code"
+                } else if f.link {
+                    "link"
+                } else if f.strike {
+                    "strike"
+                } else if f.bold {
+                    "bold"
+                } else if f.italic {
+                    "italic"
+                } else if f.done {
+                    "done"
+                } else {
+                    "text"
+                };
+                (s.text.as_str(), tag)
+            })
+            .collect()
+    }
+
+    /// The line as HTML-like text: markup left out, emphasis, strikethrough
+    /// and code shown as tags, nested in the order they open
+    fn render(line: &str) -> String {
+        let mut out = String::new();
+        let mut open: Vec<&str> = Vec::new();
+        for (ch, f, _) in visible_chars(&parse(line), true) {
+            let wanted = [
+                ("em", f.italic),
+                ("strong", f.bold),
+                ("del", f.strike),
+                ("code", f.code),
+            ];
+            let is_wanted = |tag: &str| wanted.iter().any(|&(t, on)| t == tag && on);
+            let keep = open.iter().take_while(|t| is_wanted(t)).count();
+            for tag in open.drain(keep..).rev() {
+                out.push_str(&format!("</{tag}>"));
+            }
+            for (tag, on) in wanted {
+                if on && !open.contains(&tag) {
+                    out.push_str(&format!("<{tag}>"));
+                    open.push(tag);
+                }
+            }
+            out.push(ch);
+        }
+        for tag in open.iter().rev() {
+            out.push_str(&format!("</{tag}>"));
+        }
+        out
+    }
+
+    #[test]
+    fn test_segments_keep_every_character() {
+        for line in [
+            "### A *mixed* line with `code` ###",
+            "> - [x] done, ~~struck~~ and [a link](https://example.com)",
+            "1. plain \\*not italic\\*",
+            "unclosed **bold and `tick",
+        ] {
+            let joined: String = parse(line).iter().map(|s| s.text.as_str()).collect();
+            assert_eq!(joined, line);
+        }
+    }
+
+    #[test]
+    fn test_headings_one_to_six() {
+        for level in 1..=6 {
+            let line = format!("{} Title", "#".repeat(level));
+            let segments = parse(&line);
+            assert_eq!(segments[1].format.heading, level as u8);
+            assert_eq!(segments[1].text, "Title");
+        }
+        // Seven hashes, or no space, is just text
+        assert_eq!(parse("####### Title")[0].format.heading, 0);
+        assert_eq!(parse("#hashtag")[0].format.heading, 0);
+        // A closing run of hashes is markup
+        assert_eq!(
+            tagged(&parse("## Title ##")),
+            vec![("## ", "markup"), ("Title", "text"), (" ##", "markup")]
+        );
+    }
+
+    #[test]
+    fn test_lists_tasks_and_rules() {
+        assert_eq!(
+            tagged(&parse("  - [x] ship it")),
+            vec![("  ", "text"), ("- [x] ", "bullet"), ("ship it", "done")]
+        );
+        assert_eq!(
+            tagged(&parse("12) twelfth")),
+            vec![("12) ", "bullet"), ("twelfth", "text")]
+        );
+        assert_eq!(tagged(&parse("* * *")), vec![("* * *", "rule")]);
+        assert_eq!(tagged(&parse("---")), vec![("---", "rule")]);
+        assert_eq!(tagged(&parse("-not a list")), vec![("-not a list", "text")]);
+    }
+
+    #[test]
+    fn test_inline_spans() {
+        assert_eq!(
+            tagged(&parse("`a*b*` then ~~gone~~")),
+            vec![
+                ("`", "markup"),
+                ("a*b*", "code"),
+                ("`", "markup"),
+                (" then ", "text"),
+                ("~~", "markup"),
+                ("gone", "strike"),
+                ("~~", "markup"),
+            ]
+        );
+        assert_eq!(
+            tagged(&parse("see [the **docs**](x.md)")),
+            vec![
+                ("see ", "text"),
+                ("[", "markup"),
+                ("the ", "link"),
+                ("**", "markup"),
+                ("docs", "link"),
+                ("**", "markup"),
+                ("](x.md)", "markup"),
+            ]
+        );
+        let quoted = parse("> quoted *words*");
+        assert!(quoted[1].format.quote);
+        assert!(quoted[3].format.quote && quoted[3].format.italic);
+    }
+
+    #[test]
+    fn test_emphasis_follows_commonmark() {
+        const CASES: &[(&str, &str)] = &[
+            (
+                "*italic* and **bold**",
+                "<em>italic</em> and <strong>bold</strong>",
+            ),
+            ("__bold__ _em_", "<strong>bold</strong> <em>em</em>"),
+            // Underscores inside words are just underscores
+            ("snake_case_names", "snake_case_names"),
+            ("foo_bar_baz", "foo_bar_baz"),
+            ("_foo_bar_", "<em>foo_bar</em>"),
+            // Asterisks may emphasise part of a word
+            ("un*frigging*believable", "un<em>frigging</em>believable"),
+            // Delimiters with spaces on both sides can't open or close
+            ("2 * 3 * 4", "2 * 3 * 4"),
+            ("a * b*", "a * b*"),
+            ("~~ not this ~~", "~~ not this ~~"),
+            // Unclosed markers stay literal instead of running to the end
+            ("**unclosed bold", "**unclosed bold"),
+            ("*open only, ~~and this", "*open only, ~~and this"),
+            ("**foo*", "*<em>foo</em>"),
+            // Nesting
+            ("***both***", "<em><strong>both</strong></em>"),
+            ("*a **b** c*", "<em>a <strong>b</strong> c</em>"),
+            ("**a *b* c**", "<strong>a <em>b</em> c</strong>"),
+            ("***strong** emph*", "<em><strong>strong</strong> emph</em>"),
+            ("~~gone *now*~~", "<del>gone <em>now</em></del>"),
+            // Rule of three
+            ("*foo**bar*", "<em>foo**bar</em>"),
+            // Escapes and code spans win over emphasis
+            ("\\*not emphasis\\*", "*not emphasis*"),
+            ("`*code*` *em*", "<code>*code*</code> <em>em</em>"),
+            ("*a `*` b*", "<em>a <code>*</code> b</em>"),
+            ("``a`b`` *c*", "<code>a`b</code> <em>c</em>"),
+            ("``unmatched *d*", "``unmatched <em>d</em>"),
+        ];
+        for &(line, expected) in CASES {
+            assert_eq!(render(line), expected, "{line:?}");
+        }
+    }
+
+    #[test]
+    fn test_hidden_markup_keeps_source_offsets() {
+        let segments = parse("## A **b**");
+        let shown: Vec<(char, usize)> = visible_chars(&segments, true)
+            .iter()
+            .map(|&(c, _, offset)| (c, offset))
+            .collect();
+        assert_eq!(shown, vec![('A', 3), (' ', 4), ('b', 7)]);
+        assert_eq!(visible_chars(&segments, false).len(), 10);
+    }
+
+    #[test]
+    fn test_fence_state_spans_lines() {
+        let mut state = BlockState::default();
+        let first = parse_segments("```rust", &mut state);
+        assert!(first[0].format.markup);
+        assert_ne!(state, BlockState::default());
+
+        // Markdown inside the fence is left alone
+        let body = parse_segments("# not a *heading*", &mut state);
+        assert_eq!(body.len(), 1);
+        assert!(body[0].format.code);
+
+        // A shorter or different fence doesn't close it
+        parse_segments("~~~", &mut state);
+        assert_ne!(state, BlockState::default());
+        parse_segments("````", &mut state);
+        assert_eq!(state, BlockState::default());
+    }
+
+    #[test]
+    fn test_trailing_whitespace_keeps_its_chars() {
+        let segments = parse("*a*  ");
+        let text: String = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(text, "*a*  ");
+        assert_eq!(
+            visible_chars(&segments, true).last(),
+            Some(&(' ', Format::default(), 4))
+        );
+
+        let mut state = BlockState::default();
+        parse_segments("```", &mut state);
+        let body = parse_segments("code  ", &mut state);
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].text, "code  ");
+        assert!(body[0].format.code);
+    }
+}
@@ -0,0 +1,177 @@
+//! Cursor movement over a rope. Each function takes the cursor's char index
+//! and returns where it ends up; a move that can't be made leaves it put.
+
+use crate::wrap::{self, Row};
+use ropey::Rope;
+
+/// `(column, line)` of char index `idx`, both counting from 0
+pub fn position(text: &Rope, idx: usize) -> (usize, usize) {
+    let row = text.char_to_line(idx);
+    (idx - text.line_to_char(row), row)
+}
+
+/// Length of logical line `row` in chars, without its newline
+pub fn line_len(text: &Rope, row: usize) -> usize {
+    let line = text.line(row);
+    let len = line.len_chars();
+    if len > 0 && line.char(len - 1) == '\n' {
+        len - 1
+    } else {
+        len
+    }
+}
+
+/// Start of line `line`, counting from 1 and clamped to the last line
+pub fn goto_line(text: &Rope, line: usize) -> usize {
+    let row = line.saturating_sub(1).min(text.len_lines() - 1);
+    text.line_to_char(row)
+}
+
+pub fn line_start(text: &Rope, idx: usize) -> usize {
+    text.line_to_char(text.char_to_line(idx))
+}
+
+/// End of the cursor's line, before its newline
+pub fn line_end(text: &Rope, idx: usize) -> usize {
+    let row = text.char_to_line(idx);
+    text.line_to_char(row) + line_len(text, row)
+}
+
+/// The same column on the line above, or the end of that line if it is shorter
+pub fn line_up(text: &Rope, idx: usize) -> usize {
+    let (col, row) = position(text, idx);
+    if row == 0 {
+        return idx;
+    }
+    text.line_to_char(row - 1) + col.min(line_len(text, row - 1))
+}
+
+/// The same column on the line below, or the end of that line if it is shorter
+pub fn line_down(text: &Rope, idx: usize) -> usize {
+    let (col, row) = position(text, idx);
+    if row + 1 >= text.len_lines() {
+        return idx;
+    }
+    text.line_to_char(row + 1) + col.min(line_len(text, row + 1))
+}
+
+/// Wrapped rows of logical line `row` at `width` columns
+pub fn visual_rows(text: &Rope, row: usize, width: usize) -> Vec<Row> {
+    let chars: Vec<char> = text.line(row).chars().filter(|&c| c != '\n').collect();
+    wrap::wrap_line(&chars, width)
+}
+
+/// Up one wrapped row, which may be within the same logical line
+pub fn visual_up(text: &Rope, idx: usize, width: usize) -> usize {
+    let (col, row) = position(text, idx);
+    let rows = visual_rows(text, row, width);
+    let (r, x) = wrap::locate(&rows, col);
+
+    if r > 0 {
+        let target = rows[r - 1];
+        text.line_to_char(row) + target.start + x.min(target.max_col(false))
+    } else if row > 0 {
+        let prev_rows = visual_rows(text, row - 1, width);
+        let target = prev_rows[prev_rows.len() - 1];
+        text.line_to_char(row - 1) + target.start + x.min(target.max_col(true))
+    } else {
+        idx
+    }
+}
+
+/// Down one wrapped row, which may be within the same logical line
+pub fn visual_down(text: &Rope, idx: usize, width: usize) -> usize {
+    let (col, row) = position(text, idx);
+    let rows = visual_rows(text, row, width);
+    let (r, x) = wrap::locate(&rows, col);
+
+    if r + 1 < rows.len() {
+        let target = rows[r + 1];
+        let is_last = r + 2 == rows.len();
+        text.line_to_char(row) + target.start + x.min(target.max_col(is_last))
+    } else if row + 1 < text.len_lines() {
+        let next_rows = visual_rows(text, row + 1, width);
+        let target = next_rows[0];
+        text.line_to_char(row + 1) + x.min(target.max_col(next_rows.len() == 1))
+    } else {
+        idx
+    }
+}
+
+/// Start of the word before the cursor, skipping any whitespace in between
+pub fn word_left(text: &Rope, idx: usize) -> usize {
+    if idx == 0 {
+        return 0;
+    }
+    let mut idx = idx - 1;
+
+    // Skip whitespace
+    while idx > 0 && text.char(idx).is_whitespace() {
+        idx -= 1;
+    }
+
+    // Move to start of word
+    while idx > 0 && !text.char(idx - 1).is_whitespace() {
+        idx -= 1;
+    }
+    idx
+}
+
+/// Start of the next word, past the rest of this one and the whitespace after it
+pub fn word_right(text: &Rope, idx: usize) -> usize {
+    let max_idx = text.len_chars();
+    let mut idx = idx;
+
+    // Skip current word
+    while idx < max_idx && !text.char(idx).is_whitespace() {
+        idx += 1;
+    }
+
+    // Skip whitespace
+    while idx < max_idx && text.char(idx).is_whitespace() {
+        idx += 1;
+    }
+    idx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_moves_keep_the_column_where_they_can() {
+        let text = Rope::from_str("a long line\nshort\nanother long one");
+        assert_eq!(position(&text, 9), (9, 0));
+        // Column 9 doesn't exist on "short", so land at its end
+        let down = line_down(&text, 9);
+        assert_eq!(position(&text, down), (5, 1));
+        assert_eq!(position(&text, line_down(&text, down)), (5, 2));
+        assert_eq!(line_up(&text, 3), 3);
+        assert_eq!(line_end(&text, 13), 17);
+        assert_eq!(line_start(&text, 15), 12);
+        assert_eq!(goto_line(&text, 99), 18);
+    }
+
+    #[test]
+    fn test_visual_moves_step_through_wrapped_rows() {
+        // Wraps at 10 columns into "one two ", "three four", then "five"
+        let text = Rope::from_str("one two three four\nfive");
+        assert_eq!(visual_rows(&text, 0, 10).len(), 2);
+        assert_eq!(visual_down(&text, 2, 10), 10);
+        assert_eq!(visual_down(&text, 10, 10), 21);
+        assert_eq!(visual_up(&text, 21, 10), 10);
+        assert_eq!(visual_up(&text, 10, 10), 2);
+        assert_eq!(visual_up(&text, 2, 10), 2);
+    }
+
+    #[test]
+    fn test_word_moves_skip_whitespace() {
+        let text = Rope::from_str("the  quick fox");
+        assert_eq!(word_right(&text, 0), 5);
+        assert_eq!(word_right(&text, 5), 11);
+        assert_eq!(word_right(&text, 11), 14);
+        assert_eq!(word_left(&text, 14), 11);
+        assert_eq!(word_left(&text, 11), 5);
+        assert_eq!(word_left(&text, 5), 0);
+    }
+}
@@ -0,0 +1,34 @@
+//! The typewriter's page model: a page holds a fixed number of logical
+//! lines, and page numbers count from 1.
+
+/// Page that line `row` (counting from 0) falls on
+pub fn page_of(row: usize, lines_per_page: usize) -> usize {
+    row / lines_per_page + 1
+}
+
+/// Pages filled by a text of `lines` lines. Even an empty text takes a page.
+pub fn page_count(lines: usize, lines_per_page: usize) -> usize {
+    page_of(lines.saturating_sub(1), lines_per_page)
+}
+
+/// Whether line `row` is the last one on its page, so a page break follows it
+pub fn ends_page(row: usize, lines_per_page: usize) -> bool {
+    (row + 1).is_multiple_of(lines_per_page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pages_hold_a_fixed_number_of_lines() {
+        assert_eq!(page_of(0, 54), 1);
+        assert_eq!(page_of(53, 54), 1);
+        assert_eq!(page_of(54, 54), 2);
+        assert_eq!(page_count(0, 54), 1);
+        assert_eq!(page_count(54, 54), 1);
+        assert_eq!(page_count(55, 54), 2);
+        assert!(!ends_page(52, 54));
+        assert!(ends_page(53, 54));
+    }
+}
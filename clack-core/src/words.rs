@@ -0,0 +1,35 @@
+/// Number of whitespace-separated words in a text given as chunks
+pub fn count_words<'a>(chunks: impl Iterator<Item = &'a str>) -> usize {
+    let mut words = 0;
+    let mut in_word = false;
+
+    for chunk in chunks {
+        for c in chunk.chars() {
+            if c.is_whitespace() {
+                if in_word {
+                    words += 1;
+                    in_word = false;
+                }
+            } else {
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words += 1;
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_words_may_span_chunks() {
+        assert_eq!(count_words(std::iter::empty()), 0);
+        assert_eq!(count_words(["  one two\n", "three"].into_iter()), 3);
+        // A word split across a chunk boundary counts once
+        assert_eq!(count_words(["typew", "riter keys"].into_iter()), 2);
+    }
+}
@@ -0,0 +1,130 @@
+/// One visual row of a wrapped logical line, as char offsets within the line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Row {
+    pub start: usize,
+    pub end: usize,
+    /// The row splits an over-long word; draw a hyphen after it
+    pub hyphen: bool,
+}
+
+impl Row {
+    /// Furthest column the cursor can take on this row. Non-final rows stop
+    /// one short so the position at `end` belongs to the following row.
+    pub fn max_col(&self, is_last: bool) -> usize {
+        if is_last {
+            self.end - self.start
+        } else {
+            (self.end - self.start).saturating_sub(1)
+        }
+    }
+}
+
+/// Break a line (without its trailing newline) into rows of at most `width`
+/// columns, breaking after whitespace where possible. A space that lands
+/// exactly on the margin hangs past it rather than starting the next row.
+/// Words longer than a whole row are split with a hyphen.
+pub fn wrap_line(chars: &[char], width: usize) -> Vec<Row> {
+    let width = width.max(2);
+    let len = chars.len();
+    let mut rows = Vec::new();
+    let mut pos = 0;
+
+    while len - pos > width {
+        let limit = pos + width;
+        let end = if chars[limit].is_whitespace() {
+            // The word fits exactly; let the following space hang
+            limit + 1
+        } else if let Some(b) = (pos + 1..=limit)
+            .rev()
+            .find(|&b| chars[b - 1].is_whitespace())
+        {
+            b
+        } else {
+            rows.push(Row {
+                start: pos,
+                end: limit - 1,
+                hyphen: true,
+            });
+            pos = limit - 1;
+            continue;
+        };
+        rows.push(Row {
+            start: pos,
+            end,
+            hyphen: false,
+        });
+        pos = end;
+    }
+
+    rows.push(Row {
+        start: pos,
+        end: len,
+        hyphen: false,
+    });
+    rows
+}
+
+/// Index of the row holding the cursor at `col`, and the cursor's column within it
+pub fn locate(rows: &[Row], col: usize) -> (usize, usize) {
+    let idx = rows
+        .iter()
+        .position(|r| col < r.end)
+        .unwrap_or(rows.len() - 1);
+    (idx, col - rows[idx].start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows_of(text: &str, width: usize) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        wrap_line(&chars, width)
+            .iter()
+            .map(|r| {
+                let mut s: String = chars[r.start..r.end].iter().collect();
+                if r.hyphen {
+                    s.push('-');
+                }
+                s
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_short_line_is_one_row() {
+        assert_eq!(rows_of("hello", 10), vec!["hello"]);
+        assert_eq!(rows_of("", 10), vec![""]);
+    }
+
+    #[test]
+    fn test_breaks_after_whitespace() {
+        assert_eq!(
+            rows_of("the quick brown fox", 10),
+            vec!["the quick ", "brown fox"]
+        );
+    }
+
+    #[test]
+    fn test_space_at_margin_hangs() {
+        assert_eq!(rows_of("abcde fghij", 5), vec!["abcde ", "fghij"]);
+    }
+
+    #[test]
+    fn test_long_word_is_hyphenated() {
+        assert_eq!(
+            rows_of("supercalifragilistic", 8),
+            vec!["superca-", "lifragi-", "listic"]
+        );
+    }
+
+    #[test]
+    fn test_locate_cursor() {
+        let chars: Vec<char> = "the quick brown fox".chars().collect();
+        let rows = wrap_line(&chars, 10);
+        assert_eq!(locate(&rows, 0), (0, 0));
+        assert_eq!(locate(&rows, 9), (0, 9));
+        assert_eq!(locate(&rows, 10), (1, 0));
+        assert_eq!(locate(&rows, 19), (1, 9));
+    }
+}
@@ -1,35 +1,108 @@
-use crate::config::{Config, UserPreferences};
+use crate::buffer::{self, Buffer, CloseScope};
+use crate::clipboard::{self, KillRing};
+use crate::config::{Config, MarginMode, UserPreferences};
+use crate::disk::{self, DiskStatus, ExternalChange, Fingerprint};
+use crate::encoding::{self, Encoding, LineEnding, TextFormat};
+use crate::keymap::Action;
+use crate::layout::LayoutCache;
+use crate::minibuffer::{self, FilePrompt, OpenState, SaveAsState};
+use crate::project::{self, Project};
+use crate::save;
+use crate::search::{Matcher, SearchField, SearchMode, SearchState, SearchToggle};
 use crate::sound::{AudioEngine, Sound};
+use crate::swap;
 use crate::theme::{Theme, ThemeType};
+use crate::ui::ViewMap;
+use clack_core::document::Document;
+use clack_core::motion;
+use clack_core::page;
+use clack_core::words::count_words;
+use clack_core::wrap::Row;
 use ropey::Rope;
 use std::fs;
-use std::io::{self, Write};
+use std::io;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How often the open file is compared with the copy on disk
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Answer to the "unsaved changes" prompt shown when quitting
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QuitChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+/// Answer to the prompt shown when the file was changed by another program
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExternalChoice {
+    /// Replace the buffer with the version on disk
+    Reload,
+    /// Write the buffer over the version on disk
+    Overwrite,
+    /// Keep editing; saving will ask again
+    Cancel,
+}
 
 pub struct App {
-    pub content: Rope,
-    pub cursor_idx: usize,     // Absolute character index in the text
-    pub typewriter_mode: bool, // Toggle for vertical centering
-    pub focus_mode: bool,      // Toggle for dimming inactive lines
-    pub sound_enabled: bool,   // Toggle for sound effects
-    pub double_spacing: bool,  // Toggle for double spacing between lines
-    pub show_help: bool,       // Toggle for help overlay
+    pub doc: Document,                    // Text, cursor and undo history being edited
+    pub selection_anchor: Option<usize>, // Fixed end of the selection; the cursor is the moving end
+    pub typewriter_mode: bool,           // Toggle for vertical centering
+    pub focus_mode: bool,                // Toggle for dimming inactive lines
+    pub sound_enabled: bool,             // Toggle for sound effects
+    pub double_spacing: bool,            // Toggle for double spacing between lines
+    pub hide_markup: bool,               // Conceal Markdown markup except on the cursor line
+    pub margin_mode: MarginMode,         // What typing at the bell column does
+    pub remember_toggles: bool,          // Persist toggled settings to the config file
+    pub config_path: Option<PathBuf>,    // Config file that preferences are saved to
+    pub show_help: bool,                 // Toggle for help overlay
+    pub help_scroll: usize,              // Lines the help overlay is scrolled down by
+    pub quit_prompt: Option<CloseScope>, // Save/Discard/Cancel modal shown before closing or quitting
+    pub recovery_prompt: Option<PathBuf>, // Swap file from a crashed session, awaiting Restore/Discard
+    pub external_change: Option<ExternalChange>, // File changed on disk, awaiting Reload/Overwrite
+    pub format_prompt: bool,              // Line ending / encoding conversion modal
+    pub text_format: TextFormat,          // Encoding, BOM and line endings the file is saved with
     pub audio: AudioEngine,
     pub file_path: Option<PathBuf>,
+    pub untitled_dir: PathBuf, // Where documents without a file keep their swap and emergency copies
     pub current_theme_type: ThemeType,
     pub theme: Theme,
     pub status_message: Option<String>, // Status message for user feedback
     pub config: Config,                 // Application configuration
     pub has_unsaved_changes: bool,      // Track if there are unsaved modifications
     pub last_page_number: usize,        // Track current page for feed sound
+    pub view: ViewMap,                  // Text geometry from the last frame, for mouse hit-testing
+    pub layout: LayoutCache,            // Parsed and wrapped lines, kept between frames
+    pub kill_ring: KillRing,            // Internal clipboard for cut/copy/paste
+    pub clipboard_export: Option<String>, // Text waiting to be sent to the terminal clipboard
+    pub reflow_paste: bool,             // Re-wrap pasted paragraphs at the bell column
+    pub osc52_clipboard: bool,          // Mirror cut/copy to the system clipboard via OSC 52
+    pub search: Option<SearchState>,    // Open Find/Replace prompt
+    pub file_prompt: Option<FilePrompt>, // Open Save As prompt or Open file picker
+    pub project: Option<Project>,       // Manuscript project whose chapters are being edited
+    pub navigator: Option<usize>,       // Chapter navigator overlay, with the highlighted chapter
     cached_word_count: Option<usize>,   // Cached word count for performance
     cached_char_count: Option<usize>,   // Cached character count for performance
+    saved_revision: usize,              // History revision at the last save
+    last_saved_at: Instant,             // When the document was last written, for autosave
+    last_edit_at: Instant,              // When the buffer last changed, for idle swap writes
+    swap_stale: bool,                   // Buffer has changed since the swap file was written
+    backed_up: bool,                    // The pre-session version of the file has been backed up
+    disk: Option<Fingerprint>,          // The file as last loaded or saved, to spot outside edits
+    disk_ignored: Option<Fingerprint>,  // Outside version the writer chose to keep editing over
+    last_disk_check: Instant,           // When the file was last compared with the disk
+    saved_format: TextFormat,           // Format of the file as last loaded or saved
+    buffers: Vec<Buffer>,               // Open documents; the active one's slot is a placeholder
+    active_buffer: usize, // Index in `buffers` of the document held in the fields above
+    untitled: usize,      // Number in the active document's untitled name
+    untitled_count: usize, // Highest untitled number handed out this session
+    toggled: Vec<&'static str>, // Settings toggled this session, the only ones written back
 }
 
 impl App {
-    pub fn new() -> Self {
-        // Load user preferences from config file
-        let prefs = UserPreferences::load();
+    pub fn new(prefs: UserPreferences) -> Self {
         let theme_type = prefs.parse_theme();
         let theme = match theme_type {
             ThemeType::Dark => Theme::dark(),
@@ -38,23 +111,58 @@ impl App {
         };
 
         let app = Self {
-            content: Rope::new(),
-            cursor_idx: 0,
+            doc: Document::default(),
+            selection_anchor: None,
             typewriter_mode: prefs.typewriter_mode,
             focus_mode: prefs.focus_mode,
             sound_enabled: prefs.sound_enabled,
             double_spacing: prefs.double_spacing,
+            hide_markup: prefs.hide_markup,
+            margin_mode: prefs.margin_mode,
+            remember_toggles: prefs.remember_toggles,
+            config_path: UserPreferences::config_path(),
             show_help: false,
+            help_scroll: 0,
+            quit_prompt: None,
+            recovery_prompt: None,
+            external_change: None,
+            format_prompt: false,
+            text_format: TextFormat::default(),
             audio: AudioEngine::new(prefs.sound_enabled),
             file_path: None,
+            untitled_dir: PathBuf::new(),
             current_theme_type: theme_type,
             theme,
             status_message: None,
-            config: Config::new(),
+            config: Config::from_preferences(&prefs),
             has_unsaved_changes: false,
             last_page_number: 1,
+            view: ViewMap::default(),
+            layout: LayoutCache::default(),
+            kill_ring: KillRing::default(),
+            clipboard_export: None,
+            reflow_paste: prefs.reflow_paste,
+            osc52_clipboard: prefs.osc52_clipboard,
+            search: None,
+            file_prompt: None,
+            project: None,
+            navigator: None,
             cached_word_count: None,
             cached_char_count: None,
+            saved_revision: 0,
+            last_saved_at: Instant::now(),
+            last_edit_at: Instant::now(),
+            swap_stale: false,
+            backed_up: false,
+            disk: None,
+            disk_ignored: None,
+            last_disk_check: Instant::now(),
+            saved_format: TextFormat::default(),
+            buffers: vec![Buffer::new()],
+            active_buffer: 0,
+            untitled: 1,
+            untitled_count: 1,
+            toggled: Vec::new(),
         };
 
         if app.sound_enabled {
@@ -64,51 +172,468 @@ impl App {
         app
     }
 
-    /// Create a new App instance for testing without initializing audio
-    #[cfg(test)]
-    fn new_for_test() -> Self {
-        let theme = Theme::light();
-        Self {
-            content: Rope::new(),
-            cursor_idx: 0,
-            typewriter_mode: true,
-            focus_mode: false,
-            sound_enabled: false,
-            double_spacing: false,
-            show_help: false,
-            audio: AudioEngine::new(false), // Disabled audio for tests
-            file_path: None,
-            current_theme_type: ThemeType::Light,
-            theme,
-            status_message: None,
-            config: Config::new(),
-            has_unsaved_changes: false,
-            last_page_number: 1,
-            cached_word_count: None,
-            cached_char_count: None,
+    /// Write the document to its file. A document without one is refused;
+    /// Save As picks the name.
+    pub fn save_to_file(&mut self) -> io::Result<()> {
+        let Some(path) = self.file_path.clone() else {
+            return Err(io::Error::other(format!(
+                "no file name yet; press {} to choose one",
+                self.config.keymap.label(Action::SaveAs)
+            )));
+        };
+
+        // Never clobber edits made by another program without asking
+        if let Some(current) = self.changed_on_disk() {
+            self.open_external_prompt(current);
+            return Err(io::Error::other(format!(
+                "{} was changed by another program",
+                path.display()
+            )));
         }
+
+        // Back up the version from before this session, once, on the first save
+        let mut backup_error = None;
+        if !self.backed_up {
+            match save::backup(&path, &self.config.backup) {
+                Ok(_) => self.backed_up = true,
+                Err(e) => backup_error = Some(e),
+            }
+        }
+        let bytes = encoding::encode(&self.doc.content, self.text_format).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{e}; press {} to save as UTF-8",
+                    self.config.keymap.label(Action::FileFormat)
+                ),
+            )
+        })?;
+        save::write_atomic(&path, [bytes])?;
+
+        self.saved_format = self.text_format;
+        self.disk = Fingerprint::read(&path).ok().flatten();
+        self.disk_ignored = None;
+        self.doc.history.seal();
+        self.saved_revision = self.doc.history.revision();
+        self.has_unsaved_changes = false;
+        self.last_saved_at = Instant::now();
+        self.update_chapter_stats();
+        self.discard_swap();
+        self.status_message = Some(match backup_error {
+            Some(e) => format!("Saved to {}, but backup failed: {e}", path.display()),
+            None => format!("Saved to {}", path.display()),
+        });
+        Ok(())
     }
 
-    pub fn save_to_file(&mut self) -> io::Result<()> {
-        let path = match &self.file_path {
-            Some(p) => p.clone(),
-            None => {
-                let default_path = PathBuf::from("Untitled.md");
-                self.file_path = Some(default_path.clone());
-                default_path
+    /// Periodic housekeeping, called by the event loop whether or not a key was
+    /// pressed: look for changes made to the file by other programs, autosave
+    /// on the configured interval, and refresh the swap file once typing has paused.
+    pub fn tick(&mut self, now: Instant) {
+        if now.duration_since(self.last_disk_check) >= DISK_CHECK_INTERVAL {
+            self.last_disk_check = now;
+            self.check_disk();
+        }
+        if !self.has_unsaved_changes {
+            return;
+        }
+
+        let interval = self.config.autosave.interval_secs;
+        if interval > 0
+            && self.file_path.is_some()
+            && now.duration_since(self.last_saved_at) >= Duration::from_secs(interval)
+        {
+            match self.save_to_file() {
+                Ok(()) => {
+                    if let Some(path) = &self.file_path {
+                        self.status_message = Some(format!("Autosaved to {}", path.display()));
+                    }
+                }
+                Err(e) => {
+                    // Don't retry on every tick; wait for the next interval
+                    self.last_saved_at = now;
+                    self.set_error(format!("Autosave failed: {e}"));
+                }
+            }
+            return;
+        }
+
+        let idle = Duration::from_secs(self.config.autosave.swap_idle_secs);
+        if self.config.autosave.swap_file
+            && self.swap_stale
+            && now.duration_since(self.last_edit_at) >= idle
+        {
+            if let Err(e) = self.write_swap() {
+                self.set_error(format!("Could not write recovery file: {e}"));
+            }
+            // Either way, wait for the next edit before trying again
+            self.swap_stale = false;
+        }
+    }
+
+    /// Prompt if another program has changed the file since it was loaded or
+    /// saved, unless the writer already chose to keep editing over that version
+    pub fn check_disk(&mut self) {
+        if self.external_change.is_some() {
+            return;
+        }
+        if let Some(current) = self.changed_on_disk() {
+            if self.disk_ignored != Some(current) {
+                self.open_external_prompt(current);
             }
+        }
+    }
+
+    /// The file's fingerprint if its contents no longer match what was last
+    /// loaded or saved. A deleted file isn't a conflict: saving recreates it.
+    fn changed_on_disk(&mut self) -> Option<Fingerprint> {
+        let (Some(path), Some(recorded)) = (&self.file_path, self.disk) else {
+            return None;
         };
+        match disk::check(path, &recorded) {
+            Ok(DiskStatus::Changed(current)) => Some(current),
+            Ok(DiskStatus::Touched(current)) => {
+                self.disk = Some(current);
+                None
+            }
+            Ok(DiskStatus::Unchanged | DiskStatus::Missing) | Err(_) => None,
+        }
+    }
 
-        let mut file = fs::File::create(&path)?;
-        for chunk in self.content.chunks() {
-            file.write_all(chunk.as_bytes())?;
+    fn open_external_prompt(&mut self, current: Fingerprint) {
+        self.show_help = false;
+        self.external_change = Some(ExternalChange::new(current));
+        if self.sound_enabled {
+            self.audio.trigger(Sound::Ding);
         }
+    }
 
-        self.has_unsaved_changes = false;
-        self.status_message = Some(format!("Saved to {}", path.display()));
+    /// Act on the changed-on-disk prompt. Returns true if the application
+    /// should exit (Overwrite while saving on the way out).
+    pub fn resolve_external(&mut self, choice: ExternalChoice) -> bool {
+        let Some(change) = self.external_change.take() else {
+            return false;
+        };
+        match choice {
+            ExternalChoice::Reload => {
+                self.reload_from_disk();
+                false
+            }
+            ExternalChoice::Overwrite => {
+                // Accept the disk version as seen, so the save goes ahead
+                self.disk = Some(change.disk);
+                match self.save_to_file() {
+                    Ok(()) => match change.then_close {
+                        Some(scope) => self.finish_close(scope),
+                        None => false,
+                    },
+                    Err(e) => {
+                        self.set_error(format!("Failed to save: {e}"));
+                        false
+                    }
+                }
+            }
+            ExternalChoice::Cancel => {
+                self.disk_ignored = Some(change.disk);
+                false
+            }
+        }
+    }
+
+    /// Show or hide the buffer-to-disk comparison in the changed-on-disk prompt
+    pub fn toggle_external_diff(&mut self) {
+        let Some(path) = self.file_path.clone() else {
+            return;
+        };
+        let Some(change) = &mut self.external_change else {
+            return;
+        };
+        if change.diff.take().is_some() {
+            return;
+        }
+        match disk::read_text(&path) {
+            Ok((theirs, _, _)) => {
+                change.diff = Some(disk::diff_lines(&self.doc.content.to_string(), &theirs));
+                change.scroll = 0;
+            }
+            Err(e) => self.set_error(format!("Failed to read {}: {e}", path.display())),
+        }
+    }
+
+    /// Scroll the diff by `delta` lines, stopping at either end
+    pub fn scroll_external_diff(&mut self, delta: isize) {
+        if let Some(ExternalChange {
+            diff: Some(diff),
+            scroll,
+            ..
+        }) = &mut self.external_change
+        {
+            let last = diff.len().saturating_sub(1) as isize;
+            *scroll = (*scroll as isize + delta).clamp(0, last) as usize;
+        }
+    }
+
+    /// Replace the buffer with the file on disk as one undoable edit, so the
+    /// text from before the reload is a Ctrl+Z away
+    fn reload_from_disk(&mut self) {
+        let Some(path) = self.file_path.clone() else {
+            return;
+        };
+        match disk::read_text(&path) {
+            Ok((text, format, fingerprint)) => {
+                let cursor = self.doc.cursor;
+                let end = self.doc.content.len_chars();
+                self.replace_ranges(&[(0, end, text)]);
+                self.doc.cursor = cursor.min(self.doc.content.len_chars());
+                self.doc.history.seal();
+                self.saved_revision = self.doc.history.revision();
+                self.text_format = format;
+                self.saved_format = format;
+                self.has_unsaved_changes = false;
+                self.disk = Some(fingerprint);
+                self.disk_ignored = None;
+                self.update_chapter_stats();
+                self.discard_swap();
+                self.status_message = Some(format!("Reloaded {}", path.display()));
+            }
+            Err(e) => self.set_error(format!("Failed to reload {}: {e}", path.display())),
+        }
+    }
+
+    /// The document's file, or the name it is kept under while it has none.
+    /// Each untitled buffer has its own, so their recovery copies don't collide.
+    fn recovery_base(&self) -> PathBuf {
+        match &self.file_path {
+            Some(path) => path.clone(),
+            None => self.untitled_dir.join(buffer::untitled_name(self.untitled)),
+        }
+    }
+
+    /// Swap file belonging to the current document
+    pub fn swap_path(&self) -> PathBuf {
+        swap::swap_path(&self.recovery_base())
+    }
+
+    /// Write the buffer to the swap file now
+    pub fn write_swap(&mut self) -> io::Result<()> {
+        save::write_atomic(&self.swap_path(), self.doc.content.chunks())?;
+        self.swap_stale = false;
         Ok(())
     }
 
+    /// Remove the swap file once its contents are safe (saved or deliberately discarded)
+    pub fn discard_swap(&mut self) {
+        self.swap_stale = false;
+        if let Err(e) = swap::remove(&self.swap_path()) {
+            self.set_error(format!("Could not remove recovery file: {e}"));
+        }
+    }
+
+    /// Look for a swap file left by a session that ended with unsaved changes,
+    /// and offer to restore it. A swap identical to the loaded text is just removed.
+    /// An untitled document takes over the first untitled swap that no other
+    /// open buffer owns.
+    pub fn check_recovery(&mut self) {
+        if self.file_path.is_none() {
+            if let Some(&number) = self.unclaimed_untitled_swaps().first() {
+                self.untitled = number;
+                self.untitled_count = self.untitled_count.max(number);
+            }
+        }
+        let path = self.swap_path();
+        let Ok(recovered) = fs::read_to_string(&path) else {
+            return;
+        };
+        if self.doc.content == recovered.as_str() {
+            self.discard_swap();
+        } else {
+            self.recovery_prompt = Some(path);
+        }
+    }
+
+    /// Answer the recovery prompt: replace the buffer with the swap file's text,
+    /// or throw the swap file away
+    pub fn resolve_recovery(&mut self, restore: bool) {
+        let Some(path) = self.recovery_prompt.take() else {
+            return;
+        };
+        if !restore {
+            self.discard_swap();
+        } else {
+            match fs::read_to_string(&path) {
+                Ok(recovered) => {
+                    // One undoable edit, left unsaved so the writer decides what to keep
+                    let end = self.doc.content.len_chars();
+                    self.replace_ranges(&[(0, end, recovered)]);
+                    self.status_message = Some(format!("Restored from {}", path.display()));
+                }
+                Err(e) => {
+                    self.set_error(format!("Failed to restore {}: {e}", path.display()));
+                    return;
+                }
+            }
+        }
+        // Several untitled buffers may have been left behind; offer the next
+        let others_left = self
+            .unclaimed_untitled_swaps()
+            .iter()
+            .any(|&number| number != self.untitled);
+        if self.file_path.is_none() && others_left {
+            if restore {
+                self.new_buffer();
+            }
+            self.check_recovery();
+        }
+    }
+
+    /// Numbers of the untitled swap files in `untitled_dir` that no inactive
+    /// buffer owns, lowest first
+    fn unclaimed_untitled_swaps(&self) -> Vec<usize> {
+        let dir = if self.untitled_dir.as_os_str().is_empty() {
+            std::path::Path::new(".")
+        } else {
+            &self.untitled_dir
+        };
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let claimed = |number: usize| {
+            self.buffers.iter().enumerate().any(|(idx, b)| {
+                idx != self.active_buffer && b.file_path.is_none() && b.untitled == number
+            })
+        };
+        let mut numbers: Vec<usize> = entries
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name();
+                buffer::untitled_number(swap::swapped_name(name.to_str()?)?)
+            })
+            .filter(|&number| !claimed(number))
+            .collect();
+        numbers.sort_unstable();
+        numbers
+    }
+
+    /// Ask to quit. Returns true if it is safe to exit now; otherwise switches
+    /// to the first buffer with unsaved changes, opens the Save/Discard/Cancel
+    /// prompt for it, and the caller keeps running.
+    pub fn request_quit(&mut self) -> bool {
+        if let Some(idx) = self.first_unsaved_buffer() {
+            self.switch_buffer(idx);
+            self.prompt_unsaved(CloseScope::App);
+            return false;
+        }
+        // Undoing back to the saved text can leave stale swaps behind
+        for idx in 0..self.buffers.len() {
+            self.switch_buffer(idx);
+            self.discard_swap();
+        }
+        true
+    }
+
+    /// Close the active buffer, asking first if it has unsaved changes
+    pub fn request_close_buffer(&mut self) {
+        if self.has_unsaved_changes {
+            self.prompt_unsaved(CloseScope::Buffer);
+        } else {
+            self.close_active_buffer();
+        }
+    }
+
+    fn prompt_unsaved(&mut self, scope: CloseScope) {
+        self.show_help = false;
+        self.quit_prompt = Some(scope);
+        if self.sound_enabled {
+            self.audio.trigger(Sound::Ding);
+        }
+    }
+
+    /// Act on the unsaved-changes prompt. Returns true if the application should exit.
+    pub fn resolve_quit(&mut self, choice: QuitChoice) -> bool {
+        let Some(scope) = self.quit_prompt.take() else {
+            return false;
+        };
+        match choice {
+            // Nowhere to save yet: ask for a name, then carry on closing
+            QuitChoice::Save if self.file_path.is_none() => {
+                self.open_save_as(Some(scope));
+                false
+            }
+            QuitChoice::Save => match self.save_to_file() {
+                Ok(()) => self.finish_close(scope),
+                Err(e) => {
+                    // Stay open so the writing isn't lost. If the file changed
+                    // on disk, overwriting it from that prompt finishes closing.
+                    if let Some(change) = &mut self.external_change {
+                        change.then_close = Some(scope);
+                    }
+                    self.set_error(format!("Failed to save: {e}"));
+                    false
+                }
+            },
+            QuitChoice::Discard => {
+                self.discard_swap();
+                self.finish_close(scope)
+            }
+            QuitChoice::Cancel => false,
+        }
+    }
+
+    /// The active buffer's changes are dealt with: close it, and when quitting
+    /// move on to the next unsaved buffer. Returns true if the application should exit.
+    fn finish_close(&mut self, scope: CloseScope) -> bool {
+        self.close_active_buffer();
+        match scope {
+            CloseScope::Buffer => false,
+            CloseScope::App => self.request_quit(),
+        }
+    }
+
+    /// Where the buffer is written if the session ends without anyone to ask,
+    /// e.g. when the terminal hangs up: `<file>.save` next to the document.
+    pub fn emergency_path(&self) -> PathBuf {
+        let path = self.recovery_base();
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(".save");
+        path.with_file_name(name)
+    }
+
+    /// Keep unsaved changes in every buffer when the session ends without
+    /// anyone to ask: in swap files, so the next launch offers them back, or
+    /// in `emergency_path` if swap files are turned off
+    pub fn preserve_unsaved(&mut self) -> io::Result<()> {
+        let active = self.active_buffer;
+        let mut result = Ok(());
+        for idx in 0..self.buffers.len() {
+            self.switch_buffer(idx);
+            if let Err(e) = self.preserve_active() {
+                result = Err(e);
+            }
+        }
+        self.switch_buffer(active);
+        result
+    }
+
+    fn preserve_active(&mut self) -> io::Result<()> {
+        if !self.has_unsaved_changes {
+            return Ok(());
+        }
+        if self.config.autosave.swap_file {
+            self.write_swap()
+        } else {
+            self.emergency_save().map(|_| ())
+        }
+    }
+
+    /// Write unsaved changes to `emergency_path` without touching the original file
+    pub fn emergency_save(&self) -> io::Result<Option<PathBuf>> {
+        if !self.has_unsaved_changes {
+            return Ok(None);
+        }
+        let path = self.emergency_path();
+        save::write_atomic(&path, self.doc.content.chunks())?;
+        Ok(Some(path))
+    }
+
     pub fn set_error(&mut self, message: String) {
         self.status_message = Some(format!("Error: {message}"));
     }
@@ -127,6 +652,7 @@ impl App {
         if self.sound_enabled {
             self.audio.trigger(Sound::Toggle);
         }
+        self.remember_toggle("typewriter_mode");
     }
 
     pub fn toggle_focus(&mut self) {
@@ -134,6 +660,7 @@ impl App {
         if self.sound_enabled {
             self.audio.trigger(Sound::Toggle);
         }
+        self.remember_toggle("focus_mode");
     }
 
     pub fn toggle_sound(&mut self) {
@@ -141,6 +668,7 @@ impl App {
         if self.sound_enabled {
             self.audio.trigger(Sound::Toggle);
         }
+        self.remember_toggle("sound_enabled");
     }
 
     pub fn toggle_double_spacing(&mut self) {
@@ -148,10 +676,29 @@ impl App {
         if self.sound_enabled {
             self.audio.trigger(Sound::Toggle);
         }
+        self.remember_toggle("double_spacing");
+    }
+
+    /// Switch between showing Markdown markup everywhere and only on the cursor line
+    pub fn toggle_hide_markup(&mut self) {
+        self.hide_markup = !self.hide_markup;
+        if self.sound_enabled {
+            self.audio.trigger(Sound::Toggle);
+        }
+        self.status_message = Some(
+            if self.hide_markup {
+                "Markup hidden except on the current line"
+            } else {
+                "Markup shown"
+            }
+            .to_string(),
+        );
+        self.remember_toggle("hide_markup");
     }
 
     pub fn toggle_help(&mut self) {
         self.show_help = !self.show_help;
+        self.help_scroll = 0;
         if self.sound_enabled {
             self.audio.trigger(Sound::Toggle);
         }
@@ -167,27 +714,104 @@ impl App {
         if self.sound_enabled {
             self.audio.trigger(Sound::Toggle);
         }
+        self.remember_toggle("theme");
     }
 
-    pub fn insert_char(&mut self, c: char) {
-        // Check margin before inserting character
-        let (_col, row) = self.get_cursor_position();
-        let line_len = self.content.line(row).len_chars();
+    /// Snapshot of the live settings, in config file form
+    pub fn preferences(&self) -> UserPreferences {
+        UserPreferences {
+            theme: self.current_theme_type.name().to_string(),
+            typewriter_mode: self.typewriter_mode,
+            focus_mode: self.focus_mode,
+            sound_enabled: self.sound_enabled,
+            double_spacing: self.double_spacing,
+            hide_markup: self.hide_markup,
+            remember_toggles: self.remember_toggles,
+            margin_mode: self.margin_mode,
+            reflow_paste: self.reflow_paste,
+            osc52_clipboard: self.osc52_clipboard,
+            layout: self.config.layout.clone(),
+            typewriter: self.config.typewriter.clone(),
+            autosave: self.config.autosave.clone(),
+            backup: self.config.backup.clone(),
+            keys: self.config.keys.clone(),
+        }
+    }
+
+    /// Write every current setting to the config file
+    /// Write the settings toggled this session to the config file. Others,
+    /// such as one-off command-line flags, are left as the file has them.
+    pub fn save_preferences(&mut self) {
+        let Some(path) = self.config_path.clone() else {
+            self.set_error("No config file location (HOME is not set)".to_string());
+            return;
+        };
+        if self.toggled.is_empty() {
+            self.status_message = Some("No settings changed this session".to_string());
+            return;
+        }
+        match self.preferences().save_keys(&path, &self.toggled) {
+            Ok(()) => {
+                self.status_message = Some(format!("Preferences saved to {}", path.display()))
+            }
+            Err(e) => self.set_error(format!("Failed to save preferences: {e}")),
+        }
+    }
+
+    /// Persist a single toggled setting when `remember_toggles` is on
+    fn remember_toggle(&mut self, key: &'static str) {
+        if !self.toggled.contains(&key) {
+            self.toggled.push(key);
+        }
+        if !self.remember_toggles {
+            return;
+        }
+        if let Some(path) = self.config_path.clone() {
+            if let Err(e) = self.preferences().save_keys(&path, &[key]) {
+                self.set_error(format!("Failed to save preferences: {e}"));
+            }
+        }
+    }
 
-        // Soft margin: prevent typing past bell_column (like a real typewriter margin stop)
-        if line_len >= self.config.typewriter.bell_column {
-            // Play bell to indicate margin reached
+    pub fn insert_char(&mut self, c: char) {
+        // Typing over a selection replaces it
+        if self.replace_selection(&c.to_string()) {
             if self.sound_enabled {
-                self.audio.trigger(Sound::Ding);
+                self.audio.trigger(Sound::Key);
+            }
+            return;
+        }
+
+        let (_col, row) = self.get_cursor_position();
+        let bell_column = self.config.typewriter.bell_column;
+        let rows_before = self.visual_rows(row).len();
+
+        if motion::line_len(&self.doc.content, row) >= bell_column {
+            match self.margin_mode {
+                // Margin stop: prevent typing past bell_column (like a manual typewriter)
+                MarginMode::HardStop => {
+                    if self.sound_enabled {
+                        self.audio.trigger(Sound::Ding);
+                    }
+                    return; // Don't insert the character
+                }
+                MarginMode::AutoReturn => {
+                    if !self.auto_return(c.is_whitespace()) {
+                        if self.sound_enabled {
+                            self.audio.trigger(Sound::Ding);
+                        }
+                        return;
+                    }
+                    if c.is_whitespace() {
+                        return; // The space became the line break
+                    }
+                }
+                MarginMode::Soft => {}
             }
-            return; // Don't insert the character
         }
 
         // Insert the character
-        self.content.insert_char(self.cursor_idx, c);
-        self.cursor_idx += 1;
-        self.has_unsaved_changes = true;
-        self.invalidate_count_cache();
+        self.insert_text(&c.to_string());
 
         if self.sound_enabled {
             if c == ' ' {
@@ -196,20 +820,71 @@ impl App {
                 self.audio.trigger(Sound::Key);
             }
 
-            // Bell warning when approaching margin
-            let new_line_len = self.content.line(row).len_chars();
-            if new_line_len == self.config.typewriter.bell_column {
+            let (_col, row) = self.get_cursor_position();
+            if self.margin_mode == MarginMode::Soft {
+                // Bell each time the paragraph wraps onto a new row
+                if self.visual_rows(row).len() > rows_before {
+                    self.audio.trigger(Sound::Ding);
+                }
+            } else if motion::line_len(&self.doc.content, row) == bell_column {
+                // Bell warning when reaching the margin
                 self.audio.trigger(Sound::Ding);
             }
         }
     }
 
+    /// Electric typewriter return at the margin: carry the word being typed
+    /// onto a fresh line. Only applies with the cursor at the end of the line;
+    /// returns false otherwise.
+    fn auto_return(&mut self, typed_whitespace: bool) -> bool {
+        let (col, row) = self.get_cursor_position();
+        if col != motion::line_len(&self.doc.content, row) {
+            return false;
+        }
+
+        if typed_whitespace {
+            self.insert_text("\n");
+        } else {
+            let line_start = self.doc.content.line_to_char(row);
+            let cursor = self.doc.cursor;
+
+            let mut word_start = cursor;
+            while word_start > line_start && !self.doc.content.char(word_start - 1).is_whitespace()
+            {
+                word_start -= 1;
+            }
+            // Trailing spaces before the word are dropped in favour of the break
+            let mut break_start = word_start;
+            while break_start > line_start && self.doc.content.char(break_start - 1).is_whitespace()
+            {
+                break_start -= 1;
+            }
+
+            if break_start == line_start {
+                // One word fills the whole line; break where we are
+                self.insert_text("\n");
+            } else {
+                // The word goes along with the break so the undo step, and
+                // a redo of it, leave the cursor after the word
+                let word = self.doc.content.slice(word_start..cursor).to_string();
+                self.replace_ranges(&[(break_start, cursor, format!("\n{word}"))]);
+            }
+        }
+
+        if self.sound_enabled {
+            self.audio.trigger(Sound::Return);
+        }
+        true
+    }
+
     pub fn delete_char(&mut self) {
-        if self.cursor_idx > 0 {
-            self.content.remove(self.cursor_idx - 1..self.cursor_idx);
-            self.cursor_idx -= 1;
-            self.has_unsaved_changes = true;
-            self.invalidate_count_cache();
+        if self.replace_selection("") {
+            if self.sound_enabled {
+                self.audio.trigger(Sound::Backspace);
+            }
+        } else if self.doc.cursor > 0 {
+            let before = self.doc.cursor;
+            self.remove_range(before - 1, before, before - 1);
             if self.sound_enabled {
                 self.audio.trigger(Sound::Backspace);
             }
@@ -217,10 +892,13 @@ impl App {
     }
 
     pub fn delete_char_forward(&mut self) {
-        if self.cursor_idx < self.content.len_chars() {
-            self.content.remove(self.cursor_idx..self.cursor_idx + 1);
-            self.has_unsaved_changes = true;
-            self.invalidate_count_cache();
+        if self.replace_selection("") {
+            if self.sound_enabled {
+                self.audio.trigger(Sound::Backspace);
+            }
+        } else if self.doc.cursor < self.doc.content.len_chars() {
+            let idx = self.doc.cursor;
+            self.remove_range(idx, idx + 1, idx);
             if self.sound_enabled {
                 self.audio.trigger(Sound::Backspace);
             }
@@ -228,549 +906,2285 @@ impl App {
     }
 
     pub fn enter_key(&mut self) {
-        self.content.insert_char(self.cursor_idx, '\n');
-        self.cursor_idx += 1;
-        self.has_unsaved_changes = true;
-        self.invalidate_count_cache();
+        if !self.replace_selection("\n") {
+            self.insert_text("\n");
+        }
         if self.sound_enabled {
             self.audio.trigger(Sound::Return);
         }
     }
 
-    pub fn get_cursor_position(&self) -> (usize, usize) {
-        let row = self.content.char_to_line(self.cursor_idx);
-        let row_start_idx = self.content.line_to_char(row);
-        let col = self.cursor_idx - row_start_idx;
-        (col, row)
+    /// Insert text at the cursor, recording it in the undo history
+    fn insert_text(&mut self, text: &str) {
+        let layout = &mut self.layout;
+        self.doc
+            .insert(text, |content, edit| layout.edited(content, edit));
+        self.mark_modified();
     }
 
-    pub fn get_char_count(&mut self) -> usize {
-        if let Some(count) = self.cached_char_count {
-            count
-        } else {
-            let count = self.content.len_chars();
-            self.cached_char_count = Some(count);
-            count
-        }
+    /// Remove the chars in `start..end`, recording it in the undo history
+    fn remove_range(&mut self, start: usize, end: usize, cursor_after: usize) {
+        let layout = &mut self.layout;
+        self.doc.remove(start, end, cursor_after, |content, edit| {
+            layout.edited(content, edit)
+        });
+        self.mark_modified();
     }
 
-    pub fn get_current_page(&self) -> usize {
-        let (_col, row) = self.get_cursor_position();
-        (row / self.config.typewriter.lines_per_page) + 1
+    /// Copy the selection into the kill ring (and the system clipboard)
+    pub fn copy_selection(&mut self) -> bool {
+        let Some((start, end)) = self.selection_range() else {
+            self.status_message = Some("Nothing selected".to_string());
+            return false;
+        };
+        let text = self.doc.content.slice(start..end).to_string();
+        if self.osc52_clipboard {
+            self.clipboard_export = Some(text.clone());
+        }
+        self.kill_ring.push(text);
+        true
     }
 
-    pub fn check_and_play_page_feed(&mut self) -> bool {
-        let current_page = self.get_current_page();
-        if current_page > self.last_page_number {
-            self.last_page_number = current_page;
+    pub fn cut_selection(&mut self) {
+        if self.copy_selection() {
+            self.replace_selection("");
             if self.sound_enabled {
-                self.audio.trigger(Sound::Feed);
+                self.audio.trigger(Sound::Backspace);
             }
-            true // Indicates we crossed a page boundary
-        } else {
-            false
         }
     }
 
-    pub fn get_word_count(&mut self) -> usize {
-        if let Some(count) = self.cached_word_count {
-            count
-        } else {
-            let mut words = 0;
-            let mut in_word = false;
-
-            for chunk in self.content.chunks() {
-                for c in chunk.chars() {
-                    if c.is_whitespace() {
-                        if in_word {
-                            words += 1;
-                            in_word = false;
-                        }
-                    } else {
-                        in_word = true;
-                    }
-                }
+    /// Insert the most recent kill ring entry at the cursor
+    pub fn paste_from_kill_ring(&mut self) {
+        match self.kill_ring.latest() {
+            Some(text) => {
+                let text = text.to_string();
+                self.insert_block(&text);
             }
-            if in_word {
-                words += 1;
-            }
-
-            self.cached_word_count = Some(words);
-            words
+            None => self.status_message = Some("Clipboard is empty".to_string()),
         }
     }
 
-    pub fn move_cursor_up(&mut self) {
-        let (col, row) = self.get_cursor_position();
-        if row > 0 {
-            let new_row = row - 1;
-            let new_row_start = self.content.line_to_char(new_row);
-            let new_row_len = self.content.line(new_row).len_chars();
-
-            let new_line_char_count = if new_row_len > 0 {
-                let last_char = self.content.char(new_row_start + new_row_len - 1);
-                if last_char == '\n' {
-                    new_row_len - 1
-                } else {
-                    new_row_len
-                }
-            } else {
-                0
-            };
-
-            let new_col = col.min(new_line_char_count);
-            self.cursor_idx = new_row_start + new_col;
+    /// Insert text delivered by the terminal's bracketed paste
+    pub fn paste_text(&mut self, text: &str) {
+        let mut text = clipboard::normalize_newlines(text);
+        if self.reflow_paste {
+            text = clipboard::reflow(&text, self.config.typewriter.bell_column);
         }
+        self.insert_block(&text);
     }
 
-    pub fn move_cursor_down(&mut self) -> bool {
-        let (col, row) = self.get_cursor_position();
-        if row < self.content.len_lines() - 1 {
-            let old_page = (row / self.config.typewriter.lines_per_page) + 1;
-            let new_row = row + 1;
-            let new_row_start = self.content.line_to_char(new_row);
-            let new_row_len = self.content.line(new_row).len_chars();
-
-            let new_line_char_count = if new_row_len > 0 {
-                let last_char = self.content.char(new_row_start + new_row_len - 1);
-                if last_char == '\n' {
-                    new_row_len - 1
+    /// Insert a whole block as one undo step with one keystroke sound, bypassing
+    /// the per-character margin stop
+    fn insert_block(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if !self.replace_selection(text) {
+            self.doc.history.seal();
+            self.insert_text(text);
+            self.doc.history.seal();
+        }
+        if self.sound_enabled {
+            self.audio.trigger(Sound::Key);
+        }
+    }
+
+    /// Replace the selected text with `text` as a single undo step.
+    /// Returns false (and does nothing) when there is no selection.
+    fn replace_selection(&mut self, text: &str) -> bool {
+        let Some((start, end)) = self.selection_range() else {
+            return false;
+        };
+        self.replace_ranges(&[(start, end, text.to_string())]);
+        true
+    }
+
+    /// Replace each `(start, end, text)` as a single undo step, leaving the
+    /// cursor after the last replacement. Ranges must be in document order
+    /// and must not overlap.
+    fn replace_ranges(&mut self, replacements: &[(usize, usize, String)]) {
+        if replacements.is_empty() {
+            return;
+        }
+        let layout = &mut self.layout;
+        self.doc
+            .replace_ranges(replacements, |content, edit| layout.edited(content, edit));
+        self.mark_modified();
+    }
+
+    /// Open the Find (Ctrl+F) or Replace (Ctrl+H) prompt, or switch an open one
+    pub fn open_search(&mut self, mode: SearchMode) {
+        match &mut self.search {
+            Some(search) => search.mode = mode,
+            None => self.search = Some(SearchState::new(mode, self.doc.cursor)),
+        }
+    }
+
+    pub fn close_search(&mut self) {
+        self.search = None;
+    }
+
+    pub fn search_input(&mut self, c: char) {
+        if let Some(search) = &mut self.search {
+            search.active_input().push(c);
+            if search.field == SearchField::Query {
+                self.refresh_search(true);
+            }
+        }
+    }
+
+    pub fn search_backspace(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.active_input().pop();
+            if search.field == SearchField::Query {
+                self.refresh_search(true);
+            }
+        }
+    }
+
+    /// Move between the query and replacement fields of the Replace prompt
+    pub fn search_switch_field(&mut self) {
+        if let Some(search) = &mut self.search {
+            if search.mode == SearchMode::Replace {
+                search.field = match search.field {
+                    SearchField::Query => SearchField::Replacement,
+                    SearchField::Replacement => SearchField::Query,
+                };
+            }
+        }
+    }
+
+    pub fn toggle_search_option(&mut self, toggle: SearchToggle) {
+        if let Some(search) = &mut self.search {
+            search.options.toggle(toggle);
+            if self.sound_enabled {
+                self.audio.trigger(Sound::Toggle);
+            }
+            self.refresh_search(true);
+        }
+    }
+
+    /// Recompute matches for the current query and jump to the first one at or
+    /// after the search origin (or the cursor, when `from_origin` is false)
+    fn refresh_search(&mut self, from_origin: bool) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        search.error = None;
+        search.matches.clear();
+        search.current = None;
+        if search.query.is_empty() {
+            self.doc.cursor = search.origin;
+            return;
+        }
+
+        match Matcher::new(&search.query, search.options) {
+            Ok(matcher) => search.matches = matcher.find_all(&self.doc.content),
+            Err(e) => {
+                search.error = Some(e.to_string());
+                return;
+            }
+        }
+
+        let from = if from_origin {
+            search.origin
+        } else {
+            self.doc.cursor
+        };
+        if !search.matches.is_empty() {
+            let next = search.matches.partition_point(|&(start, _)| start < from);
+            let current = if next < search.matches.len() { next } else { 0 };
+            search.current = Some(current);
+            self.doc.cursor = search.matches[current].0;
+        }
+    }
+
+    pub fn search_next(&mut self) {
+        self.step_search(true);
+    }
+
+    pub fn search_prev(&mut self) {
+        self.step_search(false);
+    }
+
+    fn step_search(&mut self, forward: bool) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let count = search.matches.len();
+        if count == 0 {
+            return;
+        }
+        let current = match search.current {
+            Some(i) if forward => (i + 1) % count,
+            Some(i) => (i + count - 1) % count,
+            None => 0,
+        };
+        search.current = Some(current);
+        self.doc.cursor = search.matches[current].0;
+    }
+
+    /// Replace the current match and move on to the next one
+    pub fn replace_current(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let Some((start, end)) = search.current_match() else {
+            return;
+        };
+        let Ok(matcher) = Matcher::new(&search.query, search.options) else {
+            return;
+        };
+        let text = self.doc.content.to_string();
+        let byte_start = self.doc.content.char_to_byte(start);
+        let replacement = matcher.replacement_for(&text, byte_start, &search.replacement);
+        self.replace_ranges(&[(start, end, replacement)]);
+        if self.sound_enabled {
+            self.audio.trigger(Sound::Key);
+        }
+        self.refresh_search(false);
+    }
+
+    pub fn replace_all(&mut self) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let Ok(matcher) = Matcher::new(&search.query, search.options) else {
+            return;
+        };
+        // Converted once; doing it per match makes a big Replace All crawl
+        let text = self.doc.content.to_string();
+        let replacements: Vec<(usize, usize, String)> = search
+            .matches
+            .iter()
+            .map(|&(start, end)| {
+                let byte_start = self.doc.content.char_to_byte(start);
+                let replacement = matcher.replacement_for(&text, byte_start, &search.replacement);
+                (start, end, replacement)
+            })
+            .collect();
+        let count = replacements.len();
+
+        self.replace_ranges(&replacements);
+        self.refresh_search(false);
+        self.status_message = Some(format!(
+            "Replaced {count} match{}",
+            if count == 1 { "" } else { "es" }
+        ));
+        if self.sound_enabled && count > 0 {
+            self.audio.trigger(Sound::Return);
+        }
+    }
+
+    fn mark_modified(&mut self) {
+        self.selection_anchor = None;
+        self.last_edit_at = Instant::now();
+        self.swap_stale = true;
+        self.refresh_unsaved();
+        self.invalidate_count_cache();
+    }
+
+    /// Unsaved if the text or its file format differs from the last save
+    fn refresh_unsaved(&mut self) {
+        self.has_unsaved_changes = self.doc.history.revision() != self.saved_revision
+            || self.text_format != self.saved_format;
+    }
+
+    pub fn undo(&mut self) {
+        let layout = &mut self.layout;
+        if self.doc.undo(|content, edit| layout.edited(content, edit)) {
+            self.mark_modified();
+            if self.sound_enabled {
+                self.audio.trigger(Sound::Backspace);
+            }
+        } else {
+            self.status_message = Some("Nothing to undo".to_string());
+        }
+    }
+
+    pub fn redo(&mut self) {
+        let layout = &mut self.layout;
+        if self.doc.redo(|content, edit| layout.edited(content, edit)) {
+            self.mark_modified();
+            if self.sound_enabled {
+                self.audio.trigger(Sound::Key);
+            }
+        } else {
+            self.status_message = Some("Nothing to redo".to_string());
+        }
+    }
+
+    /// Ordered `(start, end)` char range of the active selection, if any
+    pub fn selection_range(&self) -> Option<(usize, usize)> {
+        let anchor = self.selection_anchor?;
+        if anchor == self.doc.cursor {
+            None
+        } else {
+            Some((anchor.min(self.doc.cursor), anchor.max(self.doc.cursor)))
+        }
+    }
+
+    /// Call before moving the cursor: starts a selection when `extend` is set
+    /// (Shift held), otherwise drops any existing one
+    pub fn prepare_motion(&mut self, extend: bool) {
+        if extend {
+            if self.selection_anchor.is_none() {
+                self.selection_anchor = Some(self.doc.cursor);
+            }
+        } else {
+            self.selection_anchor = None;
+        }
+    }
+
+    /// Move the cursor to the text under a mouse position from the last frame
+    pub fn click_at(&mut self, column: u16, row: u16, extend: bool) {
+        if let Some(idx) = self.view.char_at(column, row) {
+            self.prepare_motion(extend);
+            self.doc.cursor = idx.min(self.doc.content.len_chars());
+        }
+    }
+
+    /// Replace the buffer with the contents of `path`, starting fresh undo
+    /// history. A path that doesn't exist yet starts an empty document that
+    /// will be saved there.
+    pub fn load_file(&mut self, path: PathBuf) -> io::Result<()> {
+        let (content, format, fingerprint) = if path.exists() {
+            let (text, format, fingerprint) = disk::read_text(&path)?;
+            (Rope::from_str(&text), format, Some(fingerprint))
+        } else {
+            (Rope::new(), TextFormat::default(), None)
+        };
+
+        self.doc = Document::new(content);
+        self.layout.clear();
+        self.text_format = format;
+        self.saved_format = format;
+        self.disk = fingerprint;
+        self.disk_ignored = None;
+        self.file_path = Some(path);
+        self.selection_anchor = None;
+        self.search = None;
+        self.saved_revision = 0;
+        self.has_unsaved_changes = false;
+        self.backed_up = false;
+        self.swap_stale = false;
+        self.last_saved_at = Instant::now();
+        self.last_page_number = self.get_current_page();
+        self.invalidate_count_cache();
+        self.check_recovery();
+        Ok(())
+    }
+
+    /// Open `path` in a buffer of its own, or switch to it if it's already
+    /// open. An untouched empty buffer is reused rather than kept around.
+    pub fn open_file(&mut self, path: PathBuf) {
+        if let Some(idx) = self.buffer_index_of(&path) {
+            self.switch_buffer(idx);
+            return;
+        }
+        let pristine = self.file_path.is_none()
+            && self.doc.content.len_chars() == 0
+            && !self.has_unsaved_changes;
+        if !pristine {
+            self.new_buffer();
+        }
+        match self.load_file(path.clone()) {
+            Ok(()) => self.status_message = Some(format!("Opened {}", path.display())),
+            Err(e) => {
+                if !pristine {
+                    self.close_active_buffer();
+                }
+                self.set_error(format!("Failed to load {}: {e}", path.display()));
+            }
+        }
+    }
+
+    /// Start an empty, untitled buffer and switch to it
+    pub fn new_buffer(&mut self) {
+        self.untitled_count += 1;
+        self.buffers.push(Buffer {
+            untitled: self.untitled_count,
+            ..Buffer::new()
+        });
+        self.switch_buffer(self.buffers.len() - 1);
+    }
+
+    /// Make buffer `idx` the one being edited
+    pub fn switch_buffer(&mut self, idx: usize) {
+        if idx == self.active_buffer || idx >= self.buffers.len() {
+            return;
+        }
+        self.update_chapter_stats();
+        // Protect the outgoing document's latest edits before setting it aside
+        if self.swap_stale && self.has_unsaved_changes && self.config.autosave.swap_file {
+            let _ = self.write_swap();
+        }
+        let outgoing = self.take_document();
+        self.buffers[self.active_buffer] = outgoing;
+        let incoming = std::mem::take(&mut self.buffers[idx]);
+        self.put_document(incoming);
+        self.active_buffer = idx;
+    }
+
+    /// Switch to the next (or previous) buffer, wrapping around
+    pub fn cycle_buffer(&mut self, forward: bool) {
+        let count = self.buffers.len();
+        if count < 2 {
+            self.status_message = Some("No other buffers open".to_string());
+            return;
+        }
+        let next = if forward {
+            (self.active_buffer + 1) % count
+        } else {
+            (self.active_buffer + count - 1) % count
+        };
+        self.switch_buffer(next);
+        if self.sound_enabled {
+            self.audio.trigger(Sound::Feed);
+        }
+    }
+
+    pub fn active_buffer(&self) -> usize {
+        self.active_buffer
+    }
+
+    /// Display name and unsaved flag of every open buffer, in order
+    pub fn buffer_list(&self) -> Vec<(String, bool)> {
+        self.buffers
+            .iter()
+            .enumerate()
+            .map(|(idx, b)| {
+                if idx == self.active_buffer {
+                    (
+                        buffer::display_name(self.file_path.as_deref()),
+                        self.has_unsaved_changes,
+                    )
                 } else {
-                    new_row_len
+                    (
+                        buffer::display_name(b.file_path.as_deref()),
+                        b.has_unsaved_changes,
+                    )
                 }
+            })
+            .collect()
+    }
+
+    fn first_unsaved_buffer(&self) -> Option<usize> {
+        if self.has_unsaved_changes {
+            return Some(self.active_buffer);
+        }
+        self.buffers.iter().position(|b| b.has_unsaved_changes)
+    }
+
+    fn buffer_index_of(&self, path: &std::path::Path) -> Option<usize> {
+        (0..self.buffers.len()).find(|&idx| {
+            let file = if idx == self.active_buffer {
+                self.file_path.as_deref()
             } else {
-                0
+                self.buffers[idx].file_path.as_deref()
             };
+            file.is_some_and(|f| buffer::same_file(f, path))
+        })
+    }
 
-            let new_col = col.min(new_line_char_count);
-            self.cursor_idx = new_row_start + new_col;
+    /// Drop the active buffer and switch to a neighbour, leaving a fresh
+    /// untitled buffer if it was the last one. Unsaved changes must already
+    /// have been dealt with.
+    fn close_active_buffer(&mut self) {
+        self.discard_swap();
+        self.buffers.remove(self.active_buffer);
+        if self.buffers.is_empty() {
+            self.buffers.push(Buffer::new());
+        }
+        self.active_buffer = self.active_buffer.min(self.buffers.len() - 1);
+        let incoming = std::mem::take(&mut self.buffers[self.active_buffer]);
+        self.put_document(incoming);
+    }
 
-            // Check if we crossed a page boundary
-            let new_page = (new_row / self.config.typewriter.lines_per_page) + 1;
-            if new_page > old_page {
-                // Update last_page_number if we've moved to a new highest page
-                if new_page > self.last_page_number {
-                    self.last_page_number = new_page;
-                }
-                return true; // Crossed page boundary going down
-            }
+    /// Move the active document's state out of the editing fields
+    fn take_document(&mut self) -> Buffer {
+        Buffer {
+            doc: std::mem::take(&mut self.doc),
+            file_path: self.file_path.take(),
+            untitled: self.untitled,
+            has_unsaved_changes: std::mem::take(&mut self.has_unsaved_changes),
+            last_page_number: std::mem::replace(&mut self.last_page_number, 1),
+            saved_revision: std::mem::take(&mut self.saved_revision),
+            last_saved_at: self.last_saved_at,
+            last_edit_at: self.last_edit_at,
+            swap_stale: std::mem::take(&mut self.swap_stale),
+            backed_up: std::mem::take(&mut self.backed_up),
+            disk: self.disk.take(),
+            disk_ignored: self.disk_ignored.take(),
+            text_format: std::mem::take(&mut self.text_format),
+            saved_format: std::mem::take(&mut self.saved_format),
+            layout: std::mem::take(&mut self.layout),
         }
-        false
     }
 
-    pub fn move_to_line_start(&mut self) {
-        let (_col, row) = self.get_cursor_position();
-        self.cursor_idx = self.content.line_to_char(row);
+    /// Make `buffer` the document being edited
+    fn put_document(&mut self, buffer: Buffer) {
+        self.doc = buffer.doc;
+        self.file_path = buffer.file_path;
+        self.untitled = buffer.untitled;
+        self.has_unsaved_changes = buffer.has_unsaved_changes;
+        self.last_page_number = buffer.last_page_number;
+        self.saved_revision = buffer.saved_revision;
+        self.last_saved_at = buffer.last_saved_at;
+        self.last_edit_at = buffer.last_edit_at;
+        self.swap_stale = buffer.swap_stale;
+        self.backed_up = buffer.backed_up;
+        self.disk = buffer.disk;
+        self.disk_ignored = buffer.disk_ignored;
+        self.text_format = buffer.text_format;
+        self.saved_format = buffer.saved_format;
+        self.layout = buffer.layout;
+        self.selection_anchor = None;
+        self.search = None;
+        self.invalidate_count_cache();
     }
 
-    pub fn move_to_line_end(&mut self) {
-        let (_col, row) = self.get_cursor_position();
-        let line_start = self.content.line_to_char(row);
-        let line_len = self.content.line(row).len_chars();
-
-        // Don't include the newline character in the end position
-        let line_end = if line_len > 0 {
-            let last_char_idx = line_start + line_len - 1;
-            if self.content.char(last_char_idx) == '\n' {
-                last_char_idx
-            } else {
-                line_start + line_len
+    /// Open a manuscript project (its `clack.toml`, or the directory holding
+    /// one) and start on the first chapter
+    pub fn open_project(&mut self, path: &std::path::Path) {
+        match Project::load(path) {
+            Ok(project) => {
+                let first = project.chapters[0].clone();
+                let title = project
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| project.root.display().to_string());
+                let count = project.chapters.len();
+                self.project = Some(project);
+                self.open_file(first);
+                self.status_message = Some(format!(
+                    "Project {title}: {count} chapter(s). {} shows the chapter list",
+                    self.config.keymap.label(Action::Navigator)
+                ));
             }
-        } else {
-            line_start
+            Err(e) => self.set_error(e),
+        }
+    }
+
+    /// Index of the active buffer's file among the project's chapters
+    pub fn current_chapter(&self) -> Option<usize> {
+        let project = self.project.as_ref()?;
+        project.chapter_index(self.file_path.as_deref()?)
+    }
+
+    /// Record the active chapter's latest counts for manuscript totals
+    fn update_chapter_stats(&mut self) {
+        let Some(index) = self.current_chapter() else {
+            return;
+        };
+        let stats = project::ChapterStats {
+            words: self.get_word_count(),
+            lines: self.doc.content.len_lines(),
         };
+        if let Some(project) = &mut self.project {
+            project.stats[index] = stats;
+        }
+    }
 
-        self.cursor_idx = line_end;
+    /// Words in the whole manuscript, counting the active chapter as it stands now
+    pub fn manuscript_word_count(&mut self) -> Option<usize> {
+        self.project.as_ref()?;
+        self.update_chapter_stats();
+        self.project
+            .as_ref()
+            .map(|p| p.stats.iter().map(|s| s.words).sum())
     }
 
-    pub fn move_word_left(&mut self) {
-        if self.cursor_idx == 0 {
+    pub fn toggle_navigator(&mut self) {
+        if self.navigator.is_some() {
+            self.navigator = None;
+        } else if self.project.is_some() {
+            self.update_chapter_stats();
+            self.navigator = Some(self.current_chapter().unwrap_or(0));
+        } else {
+            self.status_message = Some(format!(
+                "No project open (start clack with a directory containing {})",
+                project::MANIFEST_NAME
+            ));
+        }
+    }
+
+    /// Move the navigator highlight up (negative) or down
+    pub fn navigator_move(&mut self, delta: isize) {
+        let (Some(selected), Some(project)) = (self.navigator, &self.project) else {
+            return;
+        };
+        let last = project.chapters.len() as isize - 1;
+        self.navigator = Some((selected as isize + delta).clamp(0, last) as usize);
+    }
+
+    /// Open the highlighted chapter and close the navigator
+    pub fn navigator_open(&mut self) {
+        let Some(selected) = self.navigator.take() else {
             return;
+        };
+        if let Some(chapter) = self.project.as_ref().map(|p| p.chapters[selected].clone()) {
+            self.open_file(chapter);
         }
+    }
 
-        let mut idx = self.cursor_idx - 1;
+    /// Write every chapter, in order, into the project's output file. Open
+    /// chapters contribute their current text, including unsaved edits.
+    pub fn compile_project(&mut self) {
+        self.update_chapter_stats();
+        let Some(project) = &self.project else {
+            return;
+        };
+        let mut texts = Vec::new();
+        for chapter in &project.chapters {
+            let text = match self.buffer_index_of(chapter) {
+                Some(idx) if idx == self.active_buffer => self.doc.content.to_string(),
+                Some(idx) => self.buffers[idx].doc.content.to_string(),
+                None => match disk::read_text(chapter) {
+                    Ok((text, _, _)) => text,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+                    Err(e) => {
+                        let message = format!("Compile failed: {}: {e}", chapter.display());
+                        self.set_error(message);
+                        return;
+                    }
+                },
+            };
+            texts.push(text);
+        }
 
-        // Skip whitespace
-        while idx > 0 && self.content.char(idx).is_whitespace() {
-            idx -= 1;
+        let manuscript = project.compile(&texts);
+        let output = project.output.clone();
+        let words = count_words(std::iter::once(manuscript.as_str()));
+        let result = output
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| save::write_atomic(&output, std::iter::once(manuscript.as_str())));
+        match result {
+            Ok(()) => {
+                self.status_message = Some(format!(
+                    "Compiled {} chapter(s), {words} words, to {}",
+                    texts.len(),
+                    output.display()
+                ))
+            }
+            Err(e) => self.set_error(format!("Compile failed: {e}")),
         }
+    }
 
-        // Move to start of word
-        while idx > 0 && !self.content.char(idx - 1).is_whitespace() {
-            idx -= 1;
+    pub fn toggle_format_prompt(&mut self) {
+        self.format_prompt = !self.format_prompt;
+        if self.format_prompt {
+            self.show_help = false;
         }
+    }
 
-        self.cursor_idx = idx;
+    /// Switch the file between LF and CRLF line endings, applied on the next save
+    pub fn convert_line_endings(&mut self) {
+        self.text_format.line_ending = match self.text_format.line_ending {
+            LineEnding::Lf => LineEnding::Crlf,
+            LineEnding::Crlf => LineEnding::Lf,
+        };
+        self.format_changed();
     }
 
-    pub fn move_word_right(&mut self) {
-        let max_idx = self.content.len_chars();
-        if self.cursor_idx >= max_idx {
+    /// Switch the file between UTF-8 and Windows-1252. Refuses to switch to
+    /// Windows-1252 while the text has characters it can't represent.
+    pub fn convert_encoding(&mut self) {
+        let mut format = self.text_format;
+        format.encoding = match format.encoding {
+            Encoding::Utf8 => Encoding::Windows1252,
+            Encoding::Windows1252 => Encoding::Utf8,
+        };
+        format.bom = false;
+        if let Err(e) = encoding::encode(&self.doc.content, format) {
+            self.set_error(e.to_string());
             return;
         }
+        self.text_format = format;
+        self.format_changed();
+    }
+
+    /// Add or remove the UTF-8 byte order mark
+    pub fn toggle_bom(&mut self) {
+        if self.text_format.encoding != Encoding::Utf8 {
+            self.status_message = Some("Only UTF-8 files have a byte order mark".to_string());
+            return;
+        }
+        self.text_format.bom = !self.text_format.bom;
+        self.format_changed();
+    }
+
+    fn format_changed(&mut self) {
+        self.refresh_unsaved();
+        self.status_message = Some(format!(
+            "File format: {} (applied when saved)",
+            self.text_format
+        ));
+    }
+
+    /// Open the Save As prompt, starting from the current file name.
+    /// `then_close` is set when it was reached from the unsaved-changes prompt.
+    pub fn open_save_as(&mut self, then_close: Option<CloseScope>) {
+        let input = self
+            .file_path
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        self.search = None;
+        self.file_prompt = Some(FilePrompt::SaveAs(SaveAsState::new(input, then_close)));
+    }
+
+    /// Open the fuzzy file picker over the working directory
+    pub fn open_file_picker(&mut self) {
+        let files = minibuffer::list_files(std::path::Path::new("."));
+        self.search = None;
+        self.file_prompt = Some(FilePrompt::Open(OpenState::new(files)));
+    }
+
+    pub fn close_file_prompt(&mut self) {
+        self.file_prompt = None;
+    }
+
+    pub fn file_prompt_input(&mut self, c: char) {
+        match &mut self.file_prompt {
+            Some(FilePrompt::SaveAs(state)) => {
+                state.input.push(c);
+                state.completions.clear();
+                state.confirm_overwrite = false;
+                state.error = None;
+            }
+            Some(FilePrompt::Open(state)) => {
+                state.query.push(c);
+                state.refresh();
+            }
+            None => {}
+        }
+    }
+
+    pub fn file_prompt_backspace(&mut self) {
+        match &mut self.file_prompt {
+            Some(FilePrompt::SaveAs(state)) => {
+                state.input.pop();
+                state.completions.clear();
+                state.confirm_overwrite = false;
+                state.error = None;
+            }
+            Some(FilePrompt::Open(state)) => {
+                state.query.pop();
+                state.refresh();
+            }
+            None => {}
+        }
+    }
+
+    /// Tab: complete the Save As path, or move down the picker list
+    pub fn file_prompt_complete(&mut self) {
+        match &mut self.file_prompt {
+            Some(FilePrompt::SaveAs(state)) => {
+                let (input, completions) = minibuffer::complete_path(&state.input);
+                state.input = input;
+                state.completions = completions;
+                state.confirm_overwrite = false;
+            }
+            Some(FilePrompt::Open(state)) => state.move_selection(1),
+            None => {}
+        }
+    }
+
+    /// Move the picker highlight up (negative) or down
+    pub fn file_prompt_move(&mut self, delta: isize) {
+        if let Some(FilePrompt::Open(state)) = &mut self.file_prompt {
+            state.move_selection(delta);
+        }
+    }
+
+    /// Back out of a pending overwrite confirmation without closing the prompt
+    pub fn file_prompt_decline(&mut self) {
+        match &mut self.file_prompt {
+            Some(FilePrompt::SaveAs(state)) => state.confirm_overwrite = false,
+            Some(FilePrompt::Open(_)) | None => {}
+        }
+    }
 
-        let mut idx = self.cursor_idx;
+    /// Enter in a file prompt. Asks for confirmation first when the save
+    /// would overwrite another file. Returns true if the application should
+    /// exit (Save As from the quit prompt).
+    pub fn file_prompt_confirm(&mut self) -> bool {
+        match self.file_prompt.take() {
+            Some(FilePrompt::SaveAs(mut state)) => {
+                let path = state.path();
+                if state.input.trim().is_empty() {
+                    state.error = Some("Type a file name".to_string());
+                } else if path.is_dir() {
+                    state.error = Some(format!("{} is a directory", path.display()));
+                } else if path.exists()
+                    && self.file_path.as_ref() != Some(&path)
+                    && !state.confirm_overwrite
+                {
+                    state.confirm_overwrite = true;
+                } else {
+                    let saved = self.save_as(path);
+                    return match state.then_close {
+                        Some(scope) if saved => self.finish_close(scope),
+                        _ => false,
+                    };
+                }
+                self.file_prompt = Some(FilePrompt::SaveAs(state));
+            }
+            Some(FilePrompt::Open(state)) => {
+                let Some(file) = state.selected_file().map(PathBuf::from) else {
+                    self.file_prompt = Some(FilePrompt::Open(state));
+                    return false;
+                };
+                self.file_prompt = None;
+                self.open_file(file);
+            }
+            None => {}
+        }
+        false
+    }
 
-        // Skip current word
-        while idx < max_idx && !self.content.char(idx).is_whitespace() {
-            idx += 1;
+    /// Save under a new name; the document is that file from now on.
+    /// Returns whether the save succeeded.
+    fn save_as(&mut self, path: PathBuf) -> bool {
+        let renamed = self.file_path.as_ref() != Some(&path);
+        let old_swap = self.swap_path();
+        // Put back if the save fails, so the buffer never names a file that
+        // wasn't written
+        let previous = (
+            self.file_path.clone(),
+            self.backed_up,
+            self.disk,
+            self.disk_ignored,
+        );
+        if renamed {
+            self.file_path = Some(path);
+            self.backed_up = false;
+            // Overwriting another file was already confirmed
+            self.disk = None;
+            self.disk_ignored = None;
         }
+        match self.save_to_file() {
+            Ok(()) => {
+                if renamed {
+                    // The old name's recovery file is no longer ours
+                    let _ = swap::remove(&old_swap);
+                }
+                true
+            }
+            Err(e) => {
+                if renamed {
+                    (self.file_path, self.backed_up, self.disk, self.disk_ignored) = previous;
+                }
+                self.set_error(format!("Failed to save: {e}"));
+                false
+            }
+        }
+    }
+
+    /// Put the cursor at the start of 1-based `line`, clamped to the document
+    pub fn goto_line(&mut self, line: usize) {
+        self.doc.cursor = motion::goto_line(&self.doc.content, line);
+        // Starting deep in a document shouldn't sound a page feed on the first key
+        self.last_page_number = self.get_current_page();
+    }
 
-        // Skip whitespace
-        while idx < max_idx && self.content.char(idx).is_whitespace() {
-            idx += 1;
+    pub fn get_cursor_position(&self) -> (usize, usize) {
+        motion::position(&self.doc.content, self.doc.cursor)
+    }
+
+    pub fn get_char_count(&mut self) -> usize {
+        if let Some(count) = self.cached_char_count {
+            count
+        } else {
+            let count = self.doc.content.len_chars();
+            self.cached_char_count = Some(count);
+            count
         }
+    }
+
+    pub fn get_current_page(&self) -> usize {
+        let (_col, row) = self.get_cursor_position();
+        let lines_per_page = self.config.typewriter.lines_per_page;
+        // In a project, numbering carries on from the chapters before this one
+        let earlier_pages = match (&self.project, self.current_chapter()) {
+            (Some(project), Some(index)) => project.pages_before(index, lines_per_page),
+            _ => 0,
+        };
+        earlier_pages + page::page_of(row, lines_per_page)
+    }
+
+    pub fn check_and_play_page_feed(&mut self) -> bool {
+        let current_page = self.get_current_page();
+        if current_page > self.last_page_number {
+            self.last_page_number = current_page;
+            if self.sound_enabled {
+                self.audio.trigger(Sound::Feed);
+            }
+            true // Indicates we crossed a page boundary
+        } else {
+            false
+        }
+    }
+
+    pub fn get_word_count(&mut self) -> usize {
+        if let Some(count) = self.cached_word_count {
+            count
+        } else {
+            let words = count_words(self.doc.content.chunks());
+            self.cached_word_count = Some(words);
+            words
+        }
+    }
+
+    pub fn move_cursor_up(&mut self) {
+        self.doc.cursor = motion::line_up(&self.doc.content, self.doc.cursor);
+    }
+
+    /// Move down one logical line. Returns true if a page boundary was crossed.
+    pub fn move_cursor_down(&mut self) -> bool {
+        let from = self.doc.cursor;
+        self.doc.cursor = motion::line_down(&self.doc.content, from);
+        self.crossed_page_down(from)
+    }
+
+    /// Whether moving the cursor down from `from` took it onto a later page,
+    /// noting the furthest page reached for the feed sound
+    fn crossed_page_down(&mut self, from: usize) -> bool {
+        let lines_per_page = self.config.typewriter.lines_per_page;
+        let (_col, old_row) = motion::position(&self.doc.content, from);
+        let (_col, new_row) = self.get_cursor_position();
+        let old_page = page::page_of(old_row, lines_per_page);
+        let new_page = page::page_of(new_row, lines_per_page);
+        if new_page > old_page {
+            // Update last_page_number if we've moved to a new highest page
+            if new_page > self.last_page_number {
+                self.last_page_number = new_page;
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Wrapped rows of logical line `row`, laid out the way `ui::draw` shows them
+    fn visual_rows(&self, row: usize) -> Vec<Row> {
+        motion::visual_rows(&self.doc.content, row, self.config.typewriter.bell_column)
+    }
+
+    /// Move up one visual row, which may be within the same wrapped line
+    pub fn move_visual_up(&mut self) {
+        let width = self.config.typewriter.bell_column;
+        self.doc.cursor = motion::visual_up(&self.doc.content, self.doc.cursor, width);
+    }
+
+    /// Move down one visual row. Returns true if a page boundary was crossed.
+    pub fn move_visual_down(&mut self) -> bool {
+        let from = self.doc.cursor;
+        let width = self.config.typewriter.bell_column;
+        self.doc.cursor = motion::visual_down(&self.doc.content, from, width);
+        self.crossed_page_down(from)
+    }
+
+    pub fn move_to_line_start(&mut self) {
+        self.doc.cursor = motion::line_start(&self.doc.content, self.doc.cursor);
+    }
+
+    pub fn move_to_line_end(&mut self) {
+        self.doc.cursor = motion::line_end(&self.doc.content, self.doc.cursor);
+    }
+
+    pub fn move_word_left(&mut self) {
+        self.doc.cursor = motion::word_left(&self.doc.content, self.doc.cursor);
+    }
+
+    pub fn move_word_right(&mut self) {
+        self.doc.cursor = motion::word_right(&self.doc.content, self.doc.cursor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, scratch};
+
+    #[test]
+    fn test_new_app_has_empty_content() {
+        let app = test_util::app();
+        assert_eq!(app.doc.content.len_chars(), 0);
+        assert_eq!(app.doc.cursor, 0);
+        assert!(!app.has_unsaved_changes);
+    }
+
+    #[test]
+    fn test_insert_char_increases_cursor() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        assert_eq!(app.doc.cursor, 1);
+        assert_eq!(app.doc.content.len_chars(), 1);
+        assert!(app.has_unsaved_changes);
+    }
+
+    #[test]
+    fn test_insert_multiple_chars() {
+        let mut app = test_util::app();
+        app.insert_char('h');
+        app.insert_char('i');
+        assert_eq!(app.doc.cursor, 2);
+        assert_eq!(app.doc.content.to_string(), "hi");
+    }
+
+    #[test]
+    fn test_delete_char_decreases_cursor() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        app.insert_char('b');
+        app.delete_char();
+        assert_eq!(app.doc.cursor, 1);
+        assert_eq!(app.doc.content.to_string(), "a");
+    }
+
+    #[test]
+    fn test_delete_char_at_start_does_nothing() {
+        let mut app = test_util::app();
+        app.delete_char();
+        assert_eq!(app.doc.cursor, 0);
+        assert_eq!(app.doc.content.len_chars(), 0);
+    }
+
+    #[test]
+    fn test_enter_key_adds_newline() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        app.enter_key();
+        app.insert_char('b');
+        assert_eq!(app.doc.content.to_string(), "a\nb");
+        assert_eq!(app.doc.cursor, 3);
+    }
+
+    #[test]
+    fn test_get_cursor_position_single_line() {
+        let mut app = test_util::app();
+        app.insert_char('h');
+        app.insert_char('i');
+        let (col, row) = app.get_cursor_position();
+        assert_eq!(row, 0);
+        assert_eq!(col, 2);
+    }
+
+    #[test]
+    fn test_get_cursor_position_multiline() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        app.enter_key();
+        app.insert_char('b');
+        app.insert_char('c');
+        let (col, row) = app.get_cursor_position();
+        assert_eq!(row, 1);
+        assert_eq!(col, 2);
+    }
+
+    #[test]
+    fn test_word_count_empty() {
+        let mut app = test_util::app();
+        assert_eq!(app.get_word_count(), 0);
+    }
+
+    #[test]
+    fn test_word_count_single_word() {
+        let mut app = test_util::app();
+        for c in "hello".chars() {
+            app.insert_char(c);
+        }
+        assert_eq!(app.get_word_count(), 1);
+    }
+
+    #[test]
+    fn test_word_count_multiple_words() {
+        let mut app = test_util::app();
+        for c in "hello world test".chars() {
+            app.insert_char(c);
+        }
+        assert_eq!(app.get_word_count(), 3);
+    }
+
+    #[test]
+    fn test_char_count() {
+        let mut app = test_util::app();
+        for c in "hello".chars() {
+            app.insert_char(c);
+        }
+        assert_eq!(app.get_char_count(), 5);
+    }
+
+    #[test]
+    fn test_unsaved_changes_tracking() {
+        let mut app = test_util::app();
+        assert!(!app.has_unsaved_changes);
+
+        app.insert_char('a');
+        assert!(app.has_unsaved_changes);
+
+        // Simulate a successful save
+        app.has_unsaved_changes = false;
+        assert!(!app.has_unsaved_changes);
+
+        app.delete_char();
+        assert!(app.has_unsaved_changes);
+    }
+
+    #[test]
+    fn test_toggle_mode() {
+        let mut app = test_util::app();
+        assert!(app.typewriter_mode); // Now defaults to true
+        app.toggle_mode();
+        assert!(!app.typewriter_mode);
+        app.toggle_mode();
+        assert!(app.typewriter_mode);
+    }
+
+    #[test]
+    fn test_toggle_focus() {
+        let mut app = test_util::app();
+        assert!(!app.focus_mode);
+        app.toggle_focus();
+        assert!(app.focus_mode);
+        app.toggle_focus();
+        assert!(!app.focus_mode);
+    }
+
+    #[test]
+    fn test_save_preferences_writes_only_toggled_settings() {
+        let dir = scratch("clack_test_toggled_prefs");
+        let path = dir.join("config.toml");
+        fs::write(&path, "sound_enabled = true\n\n[layout]\ntext_width = 72\n").unwrap();
+        let mut app = test_util::app();
+        app.config_path = Some(path.clone());
+        // As if started with --width 50 and --no-sound
+        app.config.layout.text_width = 50;
+        app.sound_enabled = false;
+
+        app.save_preferences();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "sound_enabled = true\n\n[layout]\ntext_width = 72\n"
+        );
+
+        app.toggle_focus();
+        app.save_preferences();
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("focus_mode = true"));
+        assert!(saved.contains("sound_enabled = true"));
+        assert!(saved.contains("text_width = 72"));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_clear_status() {
+        let mut app = test_util::app();
+        app.set_error("test error".to_string());
+        assert!(app.status_message.is_some());
+        app.clear_status();
+        assert!(app.status_message.is_none());
+    }
+
+    #[test]
+    fn test_move_cursor_up() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        app.enter_key();
+        app.insert_char('b');
+
+        let initial_idx = app.doc.cursor;
+        app.move_cursor_up();
+        assert!(app.doc.cursor < initial_idx);
+    }
+
+    #[test]
+    fn test_move_cursor_down() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        app.enter_key();
+        app.insert_char('b');
+
+        // Move up first
+        app.move_cursor_up();
+        let idx_after_up = app.doc.cursor;
+
+        // Then move down
+        app.move_cursor_down();
+        assert!(app.doc.cursor > idx_after_up);
+    }
+
+    #[test]
+    fn test_move_to_line_start() {
+        let mut app = test_util::app();
+        for c in "hello world".chars() {
+            app.insert_char(c);
+        }
+        // Cursor is at end of line
+        assert_eq!(app.doc.cursor, 11);
+
+        app.move_to_line_start();
+        assert_eq!(app.doc.cursor, 0);
+    }
+
+    #[test]
+    fn test_move_to_line_end() {
+        let mut app = test_util::app();
+        for c in "hello world".chars() {
+            app.insert_char(c);
+        }
+        app.move_to_line_start();
+        assert_eq!(app.doc.cursor, 0);
+
+        app.move_to_line_end();
+        assert_eq!(app.doc.cursor, 11);
+    }
+
+    #[test]
+    fn test_move_word_right() {
+        let mut app = test_util::app();
+        for c in "hello world test".chars() {
+            app.insert_char(c);
+        }
+        app.move_to_line_start();
+
+        app.move_word_right();
+        // Should be at start of "world" (after "hello ")
+        assert_eq!(app.doc.cursor, 6);
+
+        app.move_word_right();
+        // Should be at start of "test"
+        assert_eq!(app.doc.cursor, 12);
+    }
+
+    #[test]
+    fn test_move_word_left() {
+        let mut app = test_util::app();
+        for c in "hello world test".chars() {
+            app.insert_char(c);
+        }
+        // Cursor at end
+
+        app.move_word_left();
+        // Should be at start of "test"
+        assert_eq!(app.doc.cursor, 12);
+
+        app.move_word_left();
+        // Should be at start of "world"
+        assert_eq!(app.doc.cursor, 6);
+
+        app.move_word_left();
+        // Should be at start of "hello"
+        assert_eq!(app.doc.cursor, 0);
+    }
+
+    #[test]
+    fn test_delete_char_forward() {
+        let mut app = test_util::app();
+        for c in "abc".chars() {
+            app.insert_char(c);
+        }
+        app.move_to_line_start();
+
+        app.delete_char_forward();
+        assert_eq!(app.doc.content.to_string(), "bc");
+        assert_eq!(app.doc.cursor, 0);
+        assert!(app.has_unsaved_changes);
+    }
+
+    #[test]
+    fn test_delete_forward_at_end() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        // Cursor at end
+        app.delete_char_forward();
+        // Should do nothing
+        assert_eq!(app.doc.content.to_string(), "a");
+    }
+
+    #[test]
+    fn test_get_current_page_empty() {
+        let app = test_util::app();
+        assert_eq!(app.get_current_page(), 1);
+    }
+
+    #[test]
+    fn test_get_current_page_single_page() {
+        let mut app = test_util::app();
+        // Add 10 lines (well below 54), cursor at end
+        for _ in 0..10 {
+            app.enter_key();
+        }
+        // Cursor is on line 10, which is page 1
+        assert_eq!(app.get_current_page(), 1);
+    }
+
+    #[test]
+    fn test_get_current_page_multiple_pages() {
+        let mut app = test_util::app();
+        // Add 54 lines, cursor ends up on line 54 (page 1)
+        for _ in 0..54 {
+            app.enter_key();
+        }
+        // Line 54 is still on page 1 (0-53 = page 1, 54-107 = page 2)
+        assert_eq!(app.get_current_page(), 2);
+
+        // Add another 54 lines
+        for _ in 0..54 {
+            app.enter_key();
+        }
+        // Now on line 108, which is page 3
+        assert_eq!(app.get_current_page(), 3);
+    }
+
+    #[test]
+    fn test_page_follows_cursor() {
+        let mut app = test_util::app();
+        // Create 100 lines
+        for _ in 0..100 {
+            app.enter_key();
+        }
+        // Cursor is at line 100, which is page 2 (54-107)
+        assert_eq!(app.get_current_page(), 2);
+
+        // Move cursor to beginning
+        app.doc.cursor = 0;
+        assert_eq!(app.get_current_page(), 1);
+
+        // Move to line 54 (start of page 2)
+        app.doc.cursor = app.doc.content.line_to_char(54);
+        assert_eq!(app.get_current_page(), 2);
+    }
+
+    #[test]
+    fn test_page_feed_detection() {
+        let mut app = test_util::app();
+        // Should be on page 1
+        assert_eq!(app.last_page_number, 1);
+
+        // Add lines up to page boundary
+        for _ in 0..53 {
+            app.enter_key();
+            assert!(!app.check_and_play_page_feed()); // Still on page 1
+        }
+
+        // Cross to page 2
+        app.enter_key();
+        assert!(app.check_and_play_page_feed()); // Should trigger feed
+        assert_eq!(app.last_page_number, 2);
+
+        // Subsequent checks shouldn't trigger again
+        assert!(!app.check_and_play_page_feed());
+    }
+
+    #[test]
+    fn test_undo_groups_typed_word() {
+        let mut app = test_util::app();
+        for c in "hello world".chars() {
+            app.insert_char(c);
+        }
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "hello ");
+        assert_eq!(app.doc.cursor, 6);
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "");
+        assert_eq!(app.doc.cursor, 0);
+    }
+
+    #[test]
+    fn test_redo_restores_undone_edit() {
+        let mut app = test_util::app();
+        for c in "abc".chars() {
+            app.insert_char(c);
+        }
+        app.delete_char();
+        app.delete_char();
+        assert_eq!(app.doc.content.to_string(), "a");
+
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "abc");
+        assert_eq!(app.doc.cursor, 3);
+
+        app.redo();
+        assert_eq!(app.doc.content.to_string(), "a");
+        assert_eq!(app.doc.cursor, 1);
+    }
+
+    #[test]
+    fn test_new_edit_clears_redo() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        app.undo();
+        app.insert_char('b');
+        app.redo();
+        assert_eq!(app.doc.content.to_string(), "b");
+    }
+
+    #[test]
+    fn test_undo_to_saved_state_clears_unsaved_flag() {
+        let dir = scratch("clack_test_undo_saved");
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("undo.md"));
+
+        app.insert_char('a');
+        app.save_to_file().unwrap();
+        assert!(!app.has_unsaved_changes);
+
+        app.insert_char('b');
+        assert!(app.has_unsaved_changes);
+
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "a");
+        assert!(!app.has_unsaved_changes);
+
+        app.undo();
+        assert!(app.has_unsaved_changes);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_shift_motion_extends_selection() {
+        let mut app = test_util::app();
+        for c in "hello world".chars() {
+            app.insert_char(c);
+        }
+        app.prepare_motion(true);
+        app.move_word_left();
+        assert_eq!(app.selection_range(), Some((6, 11)));
+
+        app.prepare_motion(false);
+        app.move_to_line_start();
+        assert_eq!(app.selection_range(), None);
+    }
+
+    #[test]
+    fn test_typing_replaces_selection_as_one_edit() {
+        let mut app = test_util::app();
+        for c in "hello world".chars() {
+            app.insert_char(c);
+        }
+        app.prepare_motion(true);
+        app.move_word_left();
+        app.insert_char('X');
+        assert_eq!(app.doc.content.to_string(), "hello X");
+        assert_eq!(app.doc.cursor, 7);
+        assert_eq!(app.selection_range(), None);
+
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "hello world");
+    }
+
+    #[test]
+    fn test_backspace_deletes_selection() {
+        let mut app = test_util::app();
+        for c in "abc def".chars() {
+            app.insert_char(c);
+        }
+        app.move_to_line_start();
+        app.prepare_motion(true);
+        app.move_word_right();
+        app.delete_char();
+        assert_eq!(app.doc.content.to_string(), "def");
+        assert_eq!(app.doc.cursor, 0);
+    }
+
+    #[test]
+    fn test_cut_and_paste_roundtrip() {
+        let mut app = test_util::app();
+        for c in "hello world".chars() {
+            app.insert_char(c);
+        }
+        app.prepare_motion(true);
+        app.move_word_left();
+        app.cut_selection();
+        assert_eq!(app.doc.content.to_string(), "hello ");
+
+        app.move_to_line_start();
+        app.paste_from_kill_ring();
+        assert_eq!(app.doc.content.to_string(), "worldhello ");
+        assert_eq!(app.doc.cursor, 5);
+    }
+
+    #[test]
+    fn test_copy_without_selection_keeps_kill_ring_empty() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        assert!(!app.copy_selection());
+        assert!(app.kill_ring.latest().is_none());
+    }
+
+    #[test]
+    fn test_bracketed_paste_is_one_edit_past_margin() {
+        let mut app = test_util::app();
+        app.insert_char('>');
+        let long_line = "x".repeat(100);
+        app.paste_text(&format!("{long_line}\r\nnext"));
+        assert_eq!(app.doc.content.to_string(), format!(">{long_line}\nnext"));
+
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), ">");
+    }
 
-        self.cursor_idx = idx;
+    #[test]
+    fn test_paste_reflows_to_bell_column() {
+        let mut app = test_util::app();
+        app.reflow_paste = true;
+        app.config.typewriter.bell_column = 10;
+        app.paste_text("one two three four\n\nfive");
+        assert_eq!(app.doc.content.to_string(), "one two\nthree four\n\nfive");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_osc52_sequence_encodes_base64() {
+        assert_eq!(clipboard::osc52_sequence("hello"), "\x1b]52;c;aGVsbG8=\x07");
+        assert_eq!(clipboard::osc52_sequence("hi!"), "\x1b]52;c;aGkh\x07");
+    }
 
     #[test]
-    fn test_new_app_has_empty_content() {
-        let app = App::new_for_test();
-        assert_eq!(app.content.len_chars(), 0);
-        assert_eq!(app.cursor_idx, 0);
-        assert!(!app.has_unsaved_changes);
+    fn test_incremental_search_moves_cursor_to_match() {
+        let mut app = test_util::app();
+        app.doc.content = Rope::from_str("cat hat cat\nthe cat");
+        app.open_search(SearchMode::Find);
+        for c in "cat".chars() {
+            app.search_input(c);
+        }
+        let search = app.search.as_ref().unwrap();
+        assert_eq!(search.matches, vec![(0, 3), (8, 11), (16, 19)]);
+        assert_eq!(app.doc.cursor, 0);
+
+        app.search_next();
+        assert_eq!(app.doc.cursor, 8);
+        app.search_prev();
+        app.search_prev();
+        assert_eq!(app.doc.cursor, 16); // wraps around
     }
 
     #[test]
-    fn test_insert_char_increases_cursor() {
-        let mut app = App::new_for_test();
-        app.insert_char('a');
-        assert_eq!(app.cursor_idx, 1);
-        assert_eq!(app.content.len_chars(), 1);
-        assert!(app.has_unsaved_changes);
+    fn test_search_options_whole_word_and_case() {
+        let mut app = test_util::app();
+        app.doc.content = Rope::from_str("Cat catalog cat");
+        app.open_search(SearchMode::Find);
+        for c in "cat".chars() {
+            app.search_input(c);
+        }
+        assert_eq!(app.search.as_ref().unwrap().matches.len(), 3);
+
+        app.toggle_search_option(SearchToggle::WholeWord);
+        assert_eq!(app.search.as_ref().unwrap().matches, vec![(0, 3), (12, 15)]);
+
+        app.toggle_search_option(SearchToggle::CaseSensitive);
+        assert_eq!(app.search.as_ref().unwrap().matches, vec![(12, 15)]);
     }
 
     #[test]
-    fn test_insert_multiple_chars() {
-        let mut app = App::new_for_test();
-        app.insert_char('h');
-        app.insert_char('i');
-        assert_eq!(app.cursor_idx, 2);
-        assert_eq!(app.content.to_string(), "hi");
+    fn test_invalid_regex_reports_error() {
+        let mut app = test_util::app();
+        app.doc.content = Rope::from_str("abc");
+        app.open_search(SearchMode::Find);
+        app.toggle_search_option(SearchToggle::Regex);
+        app.search_input('(');
+        let search = app.search.as_ref().unwrap();
+        assert!(search.error.is_some());
+        assert!(search.matches.is_empty());
     }
 
     #[test]
-    fn test_delete_char_decreases_cursor() {
-        let mut app = App::new_for_test();
-        app.insert_char('a');
-        app.insert_char('b');
-        app.delete_char();
-        assert_eq!(app.cursor_idx, 1);
-        assert_eq!(app.content.to_string(), "a");
+    fn test_replace_current_then_next() {
+        let mut app = test_util::app();
+        app.doc.content = Rope::from_str("one two one");
+        app.open_search(SearchMode::Replace);
+        for c in "one".chars() {
+            app.search_input(c);
+        }
+        app.search_switch_field();
+        for c in "1".chars() {
+            app.search_input(c);
+        }
+        app.replace_current();
+        assert_eq!(app.doc.content.to_string(), "1 two one");
+        assert_eq!(app.doc.cursor, 6);
+        assert_eq!(app.search.as_ref().unwrap().matches, vec![(6, 9)]);
     }
 
     #[test]
-    fn test_delete_char_at_start_does_nothing() {
-        let mut app = App::new_for_test();
-        app.delete_char();
-        assert_eq!(app.cursor_idx, 0);
-        assert_eq!(app.content.len_chars(), 0);
+    fn test_replace_all_with_regex_is_one_undo_step() {
+        let mut app = test_util::app();
+        app.doc.content = Rope::from_str("a1 b2 c3");
+        app.open_search(SearchMode::Replace);
+        app.toggle_search_option(SearchToggle::Regex);
+        for c in r"(\w)(\d)".chars() {
+            app.search_input(c);
+        }
+        app.search_switch_field();
+        for c in "$2$1".chars() {
+            app.search_input(c);
+        }
+        app.replace_all();
+        assert_eq!(app.doc.content.to_string(), "1a 2b 3c");
+        assert_eq!(app.status_message.as_deref(), Some("Replaced 3 matches"));
+
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "a1 b2 c3");
     }
 
     #[test]
-    fn test_enter_key_adds_newline() {
-        let mut app = App::new_for_test();
-        app.insert_char('a');
-        app.enter_key();
-        app.insert_char('b');
-        assert_eq!(app.content.to_string(), "a\nb");
-        assert_eq!(app.cursor_idx, 3);
+    fn test_visual_up_down_within_wrapped_line() {
+        let mut app = test_util::app();
+        app.config.typewriter.bell_column = 10;
+        app.doc.content = Rope::from_str("the quick brown fox\nend");
+        app.doc.cursor = 2;
+
+        assert!(!app.move_visual_down());
+        assert_eq!(app.doc.cursor, 12); // "brown fox" row, same column
+        app.move_visual_down();
+        assert_eq!(app.get_cursor_position(), (2, 1));
+
+        app.move_visual_up();
+        assert_eq!(app.doc.cursor, 12);
+        app.move_visual_up();
+        assert_eq!(app.doc.cursor, 2);
     }
 
     #[test]
-    fn test_get_cursor_position_single_line() {
-        let mut app = App::new_for_test();
-        app.insert_char('h');
-        app.insert_char('i');
-        let (col, row) = app.get_cursor_position();
-        assert_eq!(row, 0);
-        assert_eq!(col, 2);
+    fn test_visual_up_clamps_to_previous_row_end() {
+        let mut app = test_util::app();
+        app.config.typewriter.bell_column = 10;
+        app.doc.content = Rope::from_str("ab cd efghij klm");
+        app.doc.cursor = app.doc.content.len_chars();
+
+        app.move_visual_up();
+        // First row is "ab cd " (break after the space); stay on that row
+        assert_eq!(app.doc.cursor, 5);
     }
 
     #[test]
-    fn test_get_cursor_position_multiline() {
-        let mut app = App::new_for_test();
-        app.insert_char('a');
-        app.enter_key();
-        app.insert_char('b');
-        app.insert_char('c');
-        let (col, row) = app.get_cursor_position();
-        assert_eq!(row, 1);
-        assert_eq!(col, 2);
+    fn test_hard_stop_blocks_past_margin() {
+        let mut app = test_util::app();
+        app.config.typewriter.bell_column = 5;
+        for c in "abcdefg".chars() {
+            app.insert_char(c);
+        }
+        assert_eq!(app.doc.content.to_string(), "abcde");
     }
 
     #[test]
-    fn test_word_count_empty() {
-        let mut app = App::new_for_test();
-        assert_eq!(app.get_word_count(), 0);
+    fn test_auto_return_carries_word_to_new_line() {
+        let mut app = test_util::app();
+        app.margin_mode = MarginMode::AutoReturn;
+        app.config.typewriter.bell_column = 10;
+        for c in "the quick brown".chars() {
+            app.insert_char(c);
+        }
+        assert_eq!(app.doc.content.to_string(), "the quick\nbrown");
+        assert_eq!(app.doc.cursor, app.doc.content.len_chars());
     }
 
     #[test]
-    fn test_word_count_single_word() {
-        let mut app = App::new_for_test();
-        for c in "hello".chars() {
+    fn test_auto_return_redo_puts_cursor_after_word() {
+        let mut app = test_util::app();
+        app.margin_mode = MarginMode::AutoReturn;
+        app.config.typewriter.bell_column = 10;
+        for c in "the quickly".chars() {
             app.insert_char(c);
         }
-        assert_eq!(app.get_word_count(), 1);
+        assert_eq!(app.doc.content.to_string(), "the\nquickly");
+        app.undo();
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "the quickl");
+        app.redo();
+        assert_eq!(app.doc.content.to_string(), "the\nquickl");
+        assert_eq!(app.doc.cursor, app.doc.content.len_chars());
     }
 
     #[test]
-    fn test_word_count_multiple_words() {
-        let mut app = App::new_for_test();
-        for c in "hello world test".chars() {
+    fn test_auto_return_on_space_at_margin() {
+        let mut app = test_util::app();
+        app.margin_mode = MarginMode::AutoReturn;
+        app.config.typewriter.bell_column = 5;
+        for c in "hello world".chars() {
             app.insert_char(c);
         }
-        assert_eq!(app.get_word_count(), 3);
+        assert_eq!(app.doc.content.to_string(), "hello\nworld");
     }
 
     #[test]
-    fn test_char_count() {
-        let mut app = App::new_for_test();
-        for c in "hello".chars() {
+    fn test_soft_margin_keeps_paragraph_on_one_line() {
+        let mut app = test_util::app();
+        app.margin_mode = MarginMode::Soft;
+        app.config.typewriter.bell_column = 5;
+        for c in "hello world".chars() {
             app.insert_char(c);
         }
-        assert_eq!(app.get_char_count(), 5);
+        assert_eq!(app.doc.content.to_string(), "hello world");
+        assert_eq!(app.doc.content.len_lines(), 1);
     }
 
     #[test]
-    fn test_unsaved_changes_tracking() {
-        let mut app = App::new_for_test();
-        assert!(!app.has_unsaved_changes);
+    fn test_quit_without_changes_exits_immediately() {
+        let mut app = test_util::app();
+        assert!(app.request_quit());
+        assert!(app.quit_prompt.is_none());
+    }
 
+    #[test]
+    fn test_quit_with_changes_prompts() {
+        let mut app = test_util::app();
         app.insert_char('a');
-        assert!(app.has_unsaved_changes);
+        assert!(!app.request_quit());
+        assert_eq!(app.quit_prompt, Some(CloseScope::App));
 
-        // Simulate a successful save
-        app.has_unsaved_changes = false;
-        assert!(!app.has_unsaved_changes);
-
-        app.delete_char();
+        assert!(!app.resolve_quit(QuitChoice::Cancel));
+        assert!(app.quit_prompt.is_none());
         assert!(app.has_unsaved_changes);
+
+        assert!(!app.request_quit());
+        assert!(app.resolve_quit(QuitChoice::Discard));
     }
 
     #[test]
-    fn test_toggle_mode() {
-        let mut app = App::new_for_test();
-        assert!(app.typewriter_mode); // Now defaults to true
-        app.toggle_mode();
-        assert!(!app.typewriter_mode);
-        app.toggle_mode();
-        assert!(app.typewriter_mode);
+    fn test_quit_prompt_save_writes_file() {
+        let dir = scratch("clack_test_quit_save");
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("quit.md"));
+        app.insert_char('a');
+
+        assert!(!app.request_quit());
+        assert!(app.resolve_quit(QuitChoice::Save));
+        assert_eq!(fs::read_to_string(dir.join("quit.md")).unwrap(), "a");
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_toggle_focus() {
-        let mut app = App::new_for_test();
-        assert!(!app.focus_mode);
-        app.toggle_focus();
-        assert!(app.focus_mode);
-        app.toggle_focus();
-        assert!(!app.focus_mode);
+    fn test_emergency_save_leaves_original_alone() {
+        let dir = scratch("clack_test_emergency_save");
+        fs::write(dir.join("draft.md"), "old").unwrap();
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("draft.md"));
+        assert_eq!(app.emergency_save().unwrap(), None);
+
+        app.insert_char('n');
+        let saved = app.emergency_save().unwrap();
+        assert_eq!(saved, Some(dir.join("draft.md.save")));
+        assert_eq!(fs::read_to_string(dir.join("draft.md.save")).unwrap(), "n");
+        assert_eq!(fs::read_to_string(dir.join("draft.md")).unwrap(), "old");
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_clear_status() {
-        let mut app = App::new_for_test();
-        app.set_error("test error".to_string());
-        assert!(app.status_message.is_some());
-        app.clear_status();
-        assert!(app.status_message.is_none());
+    fn test_tick_autosaves_after_interval() {
+        let dir = scratch("clack_test_autosave");
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("auto.md"));
+        app.config.autosave.interval_secs = 30;
+        app.insert_char('a');
+
+        let start = Instant::now();
+        app.tick(start);
+        assert!(app.has_unsaved_changes);
+
+        app.tick(start + Duration::from_secs(31));
+        assert!(!app.has_unsaved_changes);
+        assert_eq!(fs::read_to_string(dir.join("auto.md")).unwrap(), "a");
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_move_cursor_up() {
-        let mut app = App::new_for_test();
+    fn test_swap_written_on_idle_and_removed_on_save() {
+        let dir = scratch("clack_test_swap_idle");
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("draft.md"));
+        let swap = dir.join(".draft.md.clack-swap");
+
         app.insert_char('a');
-        app.enter_key();
-        app.insert_char('b');
+        app.tick(Instant::now());
+        assert!(!swap.exists());
 
-        let initial_idx = app.cursor_idx;
-        app.move_cursor_up();
-        assert!(app.cursor_idx < initial_idx);
+        app.tick(Instant::now() + Duration::from_secs(3));
+        assert_eq!(fs::read_to_string(&swap).unwrap(), "a");
+
+        app.save_to_file().unwrap();
+        assert!(!swap.exists());
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_move_cursor_down() {
-        let mut app = App::new_for_test();
-        app.insert_char('a');
-        app.enter_key();
-        app.insert_char('b');
+    fn test_recovery_restores_swap_as_undoable_edit() {
+        let dir = scratch("clack_test_recovery");
+        fs::write(dir.join(".draft.md.clack-swap"), "recovered").unwrap();
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("draft.md"));
+        app.doc.content = Rope::from_str("saved");
+
+        app.check_recovery();
+        assert!(app.recovery_prompt.is_some());
+        app.resolve_recovery(true);
+        assert_eq!(app.doc.content.to_string(), "recovered");
+        assert!(app.has_unsaved_changes);
 
-        // Move up first
-        app.move_cursor_up();
-        let idx_after_up = app.cursor_idx;
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "saved");
 
-        // Then move down
-        app.move_cursor_down();
-        assert!(app.cursor_idx > idx_after_up);
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_move_to_line_start() {
-        let mut app = App::new_for_test();
-        for c in "hello world".chars() {
-            app.insert_char(c);
-        }
-        // Cursor is at end of line
-        assert_eq!(app.cursor_idx, 11);
+    fn test_recovery_discard_deletes_swap() {
+        let dir = scratch("clack_test_recovery_discard");
+        let swap = dir.join(".draft.md.clack-swap");
+        fs::write(&swap, "stale").unwrap();
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("draft.md"));
+
+        app.check_recovery();
+        app.resolve_recovery(false);
+        assert!(app.recovery_prompt.is_none());
+        assert!(!swap.exists());
+        assert_eq!(app.doc.content.to_string(), "");
+
+        fs::remove_dir_all(&dir).ok();
+    }
 
-        app.move_to_line_start();
-        assert_eq!(app.cursor_idx, 0);
+    #[test]
+    fn test_backup_taken_once_per_session() {
+        let dir = scratch("clack_test_backup_once");
+        fs::write(dir.join("draft.md"), "original").unwrap();
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("draft.md"));
+        app.config.backup.mode = crate::config::BackupMode::Tilde;
+
+        app.insert_char('a');
+        app.save_to_file().unwrap();
+        app.insert_char('b');
+        app.save_to_file().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.join("draft.md~")).unwrap(),
+            "original"
+        );
+        assert_eq!(fs::read_to_string(dir.join("draft.md")).unwrap(), "ab");
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_move_to_line_end() {
-        let mut app = App::new_for_test();
-        for c in "hello world".chars() {
-            app.insert_char(c);
-        }
-        app.move_to_line_start();
-        assert_eq!(app.cursor_idx, 0);
+    fn test_save_as_confirms_overwrite() {
+        let dir = scratch("clack_test_save_as");
+        let target = dir.join("taken.md");
+        fs::write(&target, "keep me").unwrap();
+        let mut app = test_util::app();
+        app.insert_char('a');
 
-        app.move_to_line_end();
-        assert_eq!(app.cursor_idx, 11);
+        app.open_save_as(None);
+        for c in target.display().to_string().chars() {
+            app.file_prompt_input(c);
+        }
+        assert!(!app.file_prompt_confirm());
+        assert!(matches!(
+            &app.file_prompt,
+            Some(FilePrompt::SaveAs(state)) if state.confirm_overwrite
+        ));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "keep me");
+
+        app.file_prompt_confirm();
+        assert!(app.file_prompt.is_none());
+        assert_eq!(app.file_path, Some(target.clone()));
+        assert_eq!(fs::read_to_string(&target).unwrap(), "a");
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_move_word_right() {
-        let mut app = App::new_for_test();
-        for c in "hello world test".chars() {
-            app.insert_char(c);
+    fn test_failed_save_as_keeps_the_old_name_and_swap() {
+        let dir = scratch("clack_test_save_as_fails");
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("draft.md"));
+        app.insert_char('a');
+        app.write_swap().unwrap();
+
+        app.open_save_as(None);
+        if let Some(FilePrompt::SaveAs(state)) = &mut app.file_prompt {
+            state.input = dir.join("missing/new.md").display().to_string();
         }
-        app.move_to_line_start();
+        app.file_prompt_confirm();
+        assert!(app.status_message.as_deref().unwrap().starts_with("Error"));
+        assert_eq!(app.file_path, Some(dir.join("draft.md")));
+        assert!(app.has_unsaved_changes);
+        assert_eq!(
+            fs::read_to_string(dir.join(".draft.md.clack-swap")).unwrap(),
+            "a"
+        );
 
-        app.move_word_right();
-        // Should be at start of "world" (after "hello ")
-        assert_eq!(app.cursor_idx, 6);
+        fs::remove_dir_all(&dir).ok();
+    }
 
-        app.move_word_right();
-        // Should be at start of "test"
-        assert_eq!(app.cursor_idx, 12);
+    #[test]
+    fn test_save_without_name_is_refused() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        assert!(app.save_to_file().is_err());
+        assert_eq!(app.file_path, None);
+        assert!(app.has_unsaved_changes);
     }
 
     #[test]
-    fn test_move_word_left() {
-        let mut app = App::new_for_test();
-        for c in "hello world test".chars() {
-            app.insert_char(c);
+    fn test_quit_save_without_name_asks_for_one() {
+        let dir = scratch("clack_test_quit_save_as");
+        let mut app = test_util::app();
+        app.insert_char('a');
+
+        assert!(!app.request_quit());
+        assert!(!app.resolve_quit(QuitChoice::Save));
+        assert!(app.file_prompt.is_some());
+        for c in dir.join("new.md").display().to_string().chars() {
+            app.file_prompt_input(c);
         }
-        // Cursor at end
+        assert!(app.file_prompt_confirm());
 
-        app.move_word_left();
-        // Should be at start of "test"
-        assert_eq!(app.cursor_idx, 12);
+        fs::remove_dir_all(&dir).ok();
+    }
 
-        app.move_word_left();
-        // Should be at start of "world"
-        assert_eq!(app.cursor_idx, 6);
+    #[test]
+    fn test_open_keeps_current_buffer() {
+        let dir = scratch("clack_test_open_buffer");
+        let other = dir.join("other.md");
+        fs::write(&other, "other text").unwrap();
+        let mut app = test_util::app();
+        app.file_path = Some(dir.join("current.md"));
+        app.insert_char('a');
 
-        app.move_word_left();
-        // Should be at start of "hello"
-        assert_eq!(app.cursor_idx, 0);
+        app.file_prompt = Some(FilePrompt::Open(OpenState::new(vec![other
+            .display()
+            .to_string()])));
+        app.file_prompt_confirm();
+        assert_eq!(app.doc.content.to_string(), "other text");
+        assert_eq!(app.file_path, Some(other.clone()));
+        assert!(!app.has_unsaved_changes);
+        assert_eq!(app.active_buffer(), 1);
+        assert_eq!(
+            app.buffer_list(),
+            vec![
+                ("current.md".to_string(), true),
+                ("other.md".to_string(), false)
+            ]
+        );
+
+        // The first buffer kept its text and its undo history
+        app.cycle_buffer(true);
+        assert_eq!(app.doc.content.to_string(), "a");
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "");
+
+        // Opening an already-open file switches to it
+        app.open_file(other);
+        assert_eq!(app.active_buffer(), 1);
+        assert_eq!(app.buffer_list().len(), 2);
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_delete_char_forward() {
-        let mut app = App::new_for_test();
-        for c in "abc".chars() {
-            app.insert_char(c);
-        }
-        app.move_to_line_start();
+    fn test_close_buffer_asks_when_unsaved() {
+        let mut app = test_util::app();
+        app.insert_char('a');
+        app.new_buffer();
+        app.insert_char('b');
 
-        app.delete_char_forward();
-        assert_eq!(app.content.to_string(), "bc");
-        assert_eq!(app.cursor_idx, 0);
-        assert!(app.has_unsaved_changes);
+        app.request_close_buffer();
+        assert_eq!(app.quit_prompt, Some(CloseScope::Buffer));
+        assert!(!app.resolve_quit(QuitChoice::Discard));
+        assert_eq!(app.buffer_list().len(), 1);
+        assert_eq!(app.doc.content.to_string(), "a");
+
+        // Closing the last buffer leaves an empty one behind
+        app.request_close_buffer();
+        app.resolve_quit(QuitChoice::Discard);
+        assert_eq!(app.buffer_list().len(), 1);
+        assert_eq!(app.doc.content.to_string(), "");
     }
 
     #[test]
-    fn test_delete_forward_at_end() {
-        let mut app = App::new_for_test();
+    fn test_quit_visits_each_unsaved_buffer() {
+        let mut app = test_util::app();
         app.insert_char('a');
-        // Cursor at end
-        app.delete_char_forward();
-        // Should do nothing
-        assert_eq!(app.content.to_string(), "a");
+        app.new_buffer();
+        app.new_buffer();
+        app.insert_char('c');
+
+        assert!(!app.request_quit());
+        assert_eq!(app.doc.content.to_string(), "c");
+        assert!(!app.resolve_quit(QuitChoice::Discard));
+        assert_eq!(app.quit_prompt, Some(CloseScope::App));
+        assert_eq!(app.doc.content.to_string(), "a");
+        assert!(app.resolve_quit(QuitChoice::Discard));
     }
 
     #[test]
-    fn test_get_current_page_empty() {
-        let app = App::new_for_test();
-        assert_eq!(app.get_current_page(), 1);
+    fn test_untitled_buffers_keep_separate_recovery_copies() {
+        let dir = scratch("clack_test_untitled_swaps");
+        let mut app = test_util::app();
+        app.untitled_dir = dir.clone();
+        app.insert_char('a');
+        app.new_buffer();
+        app.insert_char('b');
+        app.preserve_unsaved().unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(".Untitled.md.clack-swap")).unwrap(),
+            "a"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join(".Untitled-2.md.clack-swap")).unwrap(),
+            "b"
+        );
+
+        // Discarding one buffer leaves the other's copy alone
+        app.new_buffer();
+        app.insert_char('c');
+        app.request_close_buffer();
+        app.resolve_quit(QuitChoice::Discard);
+        assert!(dir.join(".Untitled-2.md.clack-swap").exists());
+
+        // The next session offers both back, one buffer each
+        let mut app = test_util::app();
+        app.untitled_dir = dir.clone();
+        app.check_recovery();
+        app.resolve_recovery(true);
+        assert!(app.recovery_prompt.is_some());
+        app.resolve_recovery(true);
+        assert!(app.recovery_prompt.is_none());
+        assert_eq!(app.buffer_list().len(), 2);
+        assert_eq!(app.doc.content.to_string(), "b");
+        app.switch_buffer(0);
+        assert_eq!(app.doc.content.to_string(), "a");
+
+        app.switch_buffer(1);
+        assert_eq!(
+            app.emergency_save().unwrap(),
+            Some(dir.join("Untitled-2.md.save"))
+        );
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_get_current_page_single_page() {
-        let mut app = App::new_for_test();
-        // Add 10 lines (well below 54), cursor at end
-        for _ in 0..10 {
-            app.enter_key();
-        }
-        // Cursor is on line 10, which is page 1
-        assert_eq!(app.get_current_page(), 1);
+    fn test_project_counts_pages_and_compiles() {
+        let dir = scratch("clack_test_project_app");
+        fs::write(
+            dir.join("clack.toml"),
+            "chapters = [\"one.md\", \"two.md\"]\noutput = \"book.md\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("one.md"), "one two\nthree\nfour\n").unwrap();
+        fs::write(dir.join("two.md"), "five six\n").unwrap();
+        let mut app = test_util::app();
+        app.config.typewriter.lines_per_page = 2;
+
+        app.open_project(&dir);
+        assert_eq!(app.current_chapter(), Some(0));
+        assert_eq!(app.manuscript_word_count(), Some(6));
+
+        app.toggle_navigator();
+        app.navigator_move(1);
+        app.navigator_open();
+        assert_eq!(app.current_chapter(), Some(1));
+        // Chapter one's four lines fill pages 1-2, so chapter two starts on page 3
+        assert_eq!(app.get_current_page(), 3);
+
+        app.insert_char('x');
+        assert_eq!(app.manuscript_word_count(), Some(6));
+        app.insert_char(' ');
+        assert_eq!(app.manuscript_word_count(), Some(7));
+
+        app.compile_project();
+        assert_eq!(
+            fs::read_to_string(dir.join("book.md")).unwrap(),
+            "one two\nthree\nfour\n\nx five six\n"
+        );
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_get_current_page_multiple_pages() {
-        let mut app = App::new_for_test();
-        // Add 54 lines, cursor ends up on line 54 (page 1)
-        for _ in 0..54 {
-            app.enter_key();
-        }
-        // Line 54 is still on page 1 (0-53 = page 1, 54-107 = page 2)
-        assert_eq!(app.get_current_page(), 2);
+    fn test_save_asks_before_overwriting_outside_edit() {
+        let dir = scratch("clack_test_external_save");
+        let path = dir.join("draft.md");
+        fs::write(&path, "ours").unwrap();
+        let mut app = test_util::app();
+        app.load_file(path.clone()).unwrap();
+        app.insert_char('!');
+
+        fs::write(&path, "synced from elsewhere").unwrap();
+        assert!(app.save_to_file().is_err());
+        assert!(app.external_change.is_some());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "synced from elsewhere");
+
+        app.toggle_external_diff();
+        let diff = app.external_change.as_ref().unwrap().diff.as_ref().unwrap();
+        assert_eq!(diff.len(), 2);
+
+        assert!(!app.resolve_external(ExternalChoice::Overwrite));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "!ours");
+        assert!(!app.has_unsaved_changes);
 
-        // Add another 54 lines
-        for _ in 0..54 {
-            app.enter_key();
-        }
-        // Now on line 108, which is page 3
-        assert_eq!(app.get_current_page(), 3);
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_page_follows_cursor() {
-        let mut app = App::new_for_test();
-        // Create 100 lines
-        for _ in 0..100 {
-            app.enter_key();
-        }
-        // Cursor is at line 100, which is page 2 (54-107)
-        assert_eq!(app.get_current_page(), 2);
+    fn test_tick_notices_outside_edit_and_reloads() {
+        let dir = scratch("clack_test_external_reload");
+        let path = dir.join("draft.md");
+        fs::write(&path, "before").unwrap();
+        let mut app = test_util::app();
+        app.load_file(path.clone()).unwrap();
+
+        fs::write(&path, "after the sync").unwrap();
+        app.tick(Instant::now() + DISK_CHECK_INTERVAL);
+        assert!(app.external_change.is_some());
+
+        app.resolve_external(ExternalChoice::Reload);
+        assert_eq!(app.doc.content.to_string(), "after the sync");
+        assert!(!app.has_unsaved_changes);
+        // The text from before the reload is one undo away
+        app.undo();
+        assert_eq!(app.doc.content.to_string(), "before");
+        assert!(app.has_unsaved_changes);
 
-        // Move cursor to beginning
-        app.cursor_idx = 0;
-        assert_eq!(app.get_current_page(), 1);
+        fs::remove_dir_all(&dir).ok();
+    }
 
-        // Move to line 54 (start of page 2)
-        app.cursor_idx = app.content.line_to_char(54);
-        assert_eq!(app.get_current_page(), 2);
+    #[test]
+    fn test_keep_editing_stops_repeat_prompts() {
+        let dir = scratch("clack_test_external_keep");
+        let path = dir.join("draft.md");
+        fs::write(&path, "before").unwrap();
+        let mut app = test_util::app();
+        app.load_file(path.clone()).unwrap();
+
+        fs::write(&path, "after the sync").unwrap();
+        app.check_disk();
+        app.resolve_external(ExternalChoice::Cancel);
+        app.check_disk();
+        assert!(app.external_change.is_none());
+
+        // Saving still asks, and a Save from the quit prompt closes once overwritten
+        app.insert_char('x');
+        assert!(!app.request_quit());
+        assert!(!app.resolve_quit(QuitChoice::Save));
+        assert!(app.external_change.is_some());
+        assert!(app.resolve_external(ExternalChoice::Overwrite));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "xbefore");
+
+        fs::remove_dir_all(&dir).ok();
     }
 
     #[test]
-    fn test_page_feed_detection() {
-        let mut app = App::new_for_test();
-        // Should be on page 1
-        assert_eq!(app.last_page_number, 1);
+    fn test_save_keeps_crlf_and_windows_1252() {
+        let dir = scratch("clack_test_text_format");
+        let path = dir.join("letter.txt");
+        fs::write(&path, b"Caf\xE9\r\n").unwrap();
+        let mut app = test_util::app();
+        app.load_file(path.clone()).unwrap();
+        assert_eq!(app.doc.content.to_string(), "Café\n");
+        assert_eq!(app.text_format.to_string(), "CP1252 CRLF");
+
+        app.doc.cursor = app.doc.content.len_chars();
+        app.insert_char('!');
+        app.enter_key();
+        app.save_to_file().unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"Caf\xE9\r\n!\r\n");
+
+        // A character Windows-1252 lacks can't be saved until converted
+        app.insert_char('☃');
+        assert!(app.save_to_file().is_err());
+        app.convert_encoding();
+        app.convert_line_endings();
+        app.save_to_file().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "Café\n!\n☃");
+
+        fs::remove_dir_all(&dir).ok();
+    }
 
-        // Add lines up to page boundary
-        for _ in 0..53 {
-            app.enter_key();
-            assert!(!app.check_and_play_page_feed()); // Still on page 1
-        }
+    #[test]
+    fn test_format_change_is_unsaved_until_reverted() {
+        let mut app = test_util::app();
+        app.convert_line_endings();
+        assert!(app.has_unsaved_changes);
+        app.convert_line_endings();
+        assert!(!app.has_unsaved_changes);
 
-        // Cross to page 2
-        app.enter_key();
-        assert!(app.check_and_play_page_feed()); // Should trigger feed
-        assert_eq!(app.last_page_number, 2);
+        app.insert_char('é');
+        app.convert_encoding();
+        assert_eq!(app.text_format.encoding, Encoding::Windows1252);
+        app.insert_char('☃');
+        app.convert_encoding();
+        assert_eq!(app.text_format.encoding, Encoding::Utf8);
+        // The snowman has no Windows-1252 byte, so converting back is refused
+        app.convert_encoding();
+        assert_eq!(app.text_format.encoding, Encoding::Utf8);
+    }
 
-        // Subsequent checks shouldn't trigger again
-        assert!(!app.check_and_play_page_feed());
+    #[test]
+    fn test_click_on_line_with_hidden_markup() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = test_util::app();
+        app.hide_markup = true;
+        app.doc.content = Rope::from_str("**bold** word\nsecond line");
+        app.doc.cursor = app.doc.content.len_chars();
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| crate::ui::draw(f, &mut app)).unwrap();
+
+        let (x, y) = (app.view.origin_x, app.view.origin_y);
+        // "bold word" is drawn without its asterisks
+        app.click_at(x, y, false);
+        assert_eq!(app.doc.cursor, 2);
+        app.doc.cursor = app.doc.content.len_chars();
+        app.click_at(x + 5, y, false);
+        assert_eq!(app.doc.cursor, 9);
+        // Past the end of the row lands after the whole line
+        app.doc.cursor = app.doc.content.len_chars();
+        app.click_at(x + 40, y, false);
+        assert_eq!(app.doc.cursor, 13);
+    }
+
+    #[test]
+    fn test_render_lays_out_edits_and_shows_only_the_viewport() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = test_util::app();
+        app.typewriter_mode = false;
+        let text: String = (0..200).map(|i| format!("line {i}\n")).collect();
+        app.doc.content = Rope::from_str(&text);
+        app.doc.cursor = app.doc.content.line_to_char(199);
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| crate::ui::draw(f, &mut app)).unwrap();
+
+        // Only the rows on the paper are mapped, ending at the cursor's line
+        assert!(app.view.rows.len() <= usize::from(app.view.height));
+        let last = app.view.rows.iter().flatten().last().unwrap();
+        assert_eq!(last.char_at(0), app.doc.content.line_to_char(199));
+
+        // Typing and undoing lay the touched line out again
+        app.insert_char('#');
+        app.insert_char(' ');
+        terminal.draw(|f| crate::ui::draw(f, &mut app)).unwrap();
+        let width = app.config.typewriter.bell_column;
+        assert_eq!(app.layout.line(199).segments[1].format.heading, 1);
+        assert_eq!(app.layout.len(), 201);
+
+        app.undo();
+        app.layout
+            .update(&app.doc.content, width, false, usize::MAX);
+        assert_eq!(app.layout.line(199).segments[0].text, "line 199");
+        assert_eq!(app.layout.line(199).segments[0].format.heading, 0);
     }
 }
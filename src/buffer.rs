@@ -0,0 +1,125 @@
+use crate::disk::Fingerprint;
+use crate::encoding::TextFormat;
+use crate::layout::LayoutCache;
+use clack_core::document::Document;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// An open document that isn't being edited right now. The active document
+/// lives directly in `App`'s fields; switching buffers trades the two.
+pub struct Buffer {
+    pub doc: Document,
+    pub file_path: Option<PathBuf>,
+    /// Number in the name the document is kept under while it has no file
+    pub untitled: usize,
+    pub has_unsaved_changes: bool,
+    pub last_page_number: usize,
+    pub saved_revision: usize,
+    pub last_saved_at: Instant,
+    pub last_edit_at: Instant,
+    pub swap_stale: bool,
+    pub backed_up: bool,
+    pub disk: Option<Fingerprint>,
+    pub disk_ignored: Option<Fingerprint>,
+    pub text_format: TextFormat,
+    pub saved_format: TextFormat,
+    pub layout: LayoutCache,
+}
+
+impl Default for Buffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Buffer {
+    /// A new, empty, untitled document
+    pub fn new() -> Self {
+        Self {
+            doc: Document::default(),
+            file_path: None,
+            untitled: 1,
+            has_unsaved_changes: false,
+            last_page_number: 1,
+            saved_revision: 0,
+            last_saved_at: Instant::now(),
+            last_edit_at: Instant::now(),
+            swap_stale: false,
+            backed_up: false,
+            disk: None,
+            disk_ignored: None,
+            text_format: TextFormat::default(),
+            saved_format: TextFormat::default(),
+            layout: LayoutCache::default(),
+        }
+    }
+}
+
+/// Name shown for a document in the header and prompts
+pub fn display_name(file_path: Option<&Path>) -> String {
+    file_path
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Untitled.md".to_string())
+}
+
+/// Name an untitled document's swap and emergency copies are based on:
+/// `Untitled.md` for the first, then `Untitled-2.md`, `Untitled-3.md`, …
+pub fn untitled_name(number: usize) -> String {
+    match number {
+        1 => "Untitled.md".to_string(),
+        n => format!("Untitled-{n}.md"),
+    }
+}
+
+/// The number `untitled_name` gave `name`, if it is one of its names
+pub fn untitled_number(name: &str) -> Option<usize> {
+    if name == "Untitled.md" {
+        return Some(1);
+    }
+    let n: usize = name
+        .strip_prefix("Untitled-")?
+        .strip_suffix(".md")?
+        .parse()
+        .ok()?;
+    (n > 1 && untitled_name(n) == name).then_some(n)
+}
+
+/// Whether two paths name the same file, following symlinks and `..` where possible
+pub fn same_file(a: &Path, b: &Path) -> bool {
+    a == b
+        || matches!(
+            (a.canonicalize(), b.canonicalize()),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// What the unsaved-changes prompt is guarding
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CloseScope {
+    /// Closing the active buffer
+    Buffer,
+    /// Quitting the application, one unsaved buffer at a time
+    App,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untitled_names_round_trip() {
+        for n in [1, 2, 17] {
+            assert_eq!(untitled_number(&untitled_name(n)), Some(n));
+        }
+        assert_eq!(untitled_name(2), "Untitled-2.md");
+        for name in [
+            "Untitled-1.md",
+            "Untitled-02.md",
+            "Untitled-x.md",
+            "draft.md",
+        ] {
+            assert_eq!(untitled_number(name), None, "{name}");
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::config::UserPreferences;
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use std::path::PathBuf;
+
+/// Command-line options. Anything given here overrides config.toml for this session.
+#[derive(Parser, Debug)]
+#[command(
+    name = "clack",
+    version,
+    about = "A distraction-free terminal typewriter",
+    after_help = "Examples:\n  clack notes.md\n  clack +120 draft.md --focus --no-sound\n  clack --theme retro --width 64 --bell-column 60 journal.md"
+)]
+pub struct Cli {
+    /// File to open or create, optionally preceded by +LINE to start on that line
+    #[arg(value_name = "[+LINE] FILE", num_args = 0..=2)]
+    positionals: Vec<String>,
+
+    /// Color theme
+    #[arg(long, value_name = "NAME", value_parser = ["paper", "dark", "retro"], ignore_case = true)]
+    pub theme: Option<String>,
+
+    /// Start with sound effects off
+    #[arg(long)]
+    pub no_sound: bool,
+
+    /// Start with focus mode on (dim inactive lines)
+    #[arg(long)]
+    pub focus: bool,
+
+    /// Start with typewriter mode off (don't keep the active line centered)
+    #[arg(long)]
+    pub no_typewriter: bool,
+
+    /// Start with double spacing on
+    #[arg(long)]
+    pub double: bool,
+
+    /// Start with Markdown markup hidden except on the line being edited
+    #[arg(long)]
+    pub hide_markup: bool,
+
+    /// Width of the paper's text area in characters
+    #[arg(long, value_name = "COLUMNS")]
+    pub width: Option<u16>,
+
+    /// Column where the bell rings and lines wrap
+    #[arg(long, value_name = "COLUMN")]
+    pub bell_column: Option<usize>,
+
+    /// Lines per page before the paper feeds
+    #[arg(long, value_name = "LINES")]
+    pub lines_per_page: Option<usize>,
+
+    /// Read settings from this file instead of ~/.config/clack/config.toml
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// File to open, split out of the positional arguments
+    #[arg(skip)]
+    pub file: Option<PathBuf>,
+
+    /// 1-based line to start on, from a `+LINE` argument
+    #[arg(skip)]
+    pub start_line: Option<usize>,
+}
+
+impl Cli {
+    /// Parse the process arguments, exiting with a usage error if they don't make sense
+    pub fn parse_args() -> Self {
+        let mut cli = Self::parse();
+        match cli.split_positionals() {
+            Ok((file, start_line)) => {
+                cli.file = file;
+                cli.start_line = start_line;
+            }
+            Err(message) => Self::command()
+                .error(ErrorKind::ValueValidation, message)
+                .exit(),
+        }
+        cli
+    }
+
+    fn split_positionals(&self) -> Result<(Option<PathBuf>, Option<usize>), String> {
+        let mut file = None;
+        let mut line = None;
+        for arg in &self.positionals {
+            if let Some(number) = arg.strip_prefix('+') {
+                let parsed = number.parse::<usize>().map_err(|_| {
+                    format!("invalid line number '{arg}' (expected +LINE, e.g. +42)")
+                })?;
+                if line.replace(parsed).is_some() {
+                    return Err("only one +LINE may be given".to_string());
+                }
+            } else if file.replace(PathBuf::from(arg)).is_some() {
+                return Err("only one file may be opened".to_string());
+            }
+        }
+        Ok((file, line))
+    }
+
+    /// Overlay the command-line options on preferences loaded from disk,
+    /// exiting with a usage error if the result is out of range
+    pub fn apply(&self, prefs: &mut UserPreferences) {
+        if let Some(theme) = &self.theme {
+            prefs.theme = theme.clone();
+        }
+        if self.no_sound {
+            prefs.sound_enabled = false;
+        }
+        if self.focus {
+            prefs.focus_mode = true;
+        }
+        if self.no_typewriter {
+            prefs.typewriter_mode = false;
+        }
+        if self.double {
+            prefs.double_spacing = true;
+        }
+        if self.hide_markup {
+            prefs.hide_markup = true;
+        }
+        if let Some(width) = self.width {
+            prefs.layout.text_width = width;
+            // Narrower paper pulls the margin in unless it was set explicitly
+            if self.bell_column.is_none() {
+                prefs.typewriter.bell_column = prefs.typewriter.bell_column.min(width as usize);
+            }
+        }
+        if let Some(bell_column) = self.bell_column {
+            prefs.typewriter.bell_column = bell_column;
+        }
+        if let Some(lines_per_page) = self.lines_per_page {
+            prefs.typewriter.lines_per_page = lines_per_page;
+        }
+
+        let problems = prefs.validate();
+        if !problems.is_empty() {
+            Self::command()
+                .error(ErrorKind::ValueValidation, problems.join("\n"))
+                .exit();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        let mut cli = Cli::try_parse_from(std::iter::once("clack").chain(args.iter().copied()))
+            .expect("valid arguments");
+        let (file, start_line) = cli.split_positionals().expect("valid positionals");
+        cli.file = file;
+        cli.start_line = start_line;
+        cli
+    }
+
+    #[test]
+    fn test_line_and_file_in_either_order() {
+        let cli = parse(&["+12", "draft.md"]);
+        assert_eq!(cli.file, Some(PathBuf::from("draft.md")));
+        assert_eq!(cli.start_line, Some(12));
+
+        let cli = parse(&["draft.md", "+3"]);
+        assert_eq!(cli.file, Some(PathBuf::from("draft.md")));
+        assert_eq!(cli.start_line, Some(3));
+    }
+
+    #[test]
+    fn test_two_files_are_rejected() {
+        let cli = Cli::try_parse_from(["clack", "a.md", "b.md"]).unwrap();
+        assert!(cli.split_positionals().is_err());
+    }
+
+    #[test]
+    fn test_flags_override_preferences() {
+        let cli = parse(&["--theme", "Retro", "--no-sound", "--focus", "--width", "60"]);
+        let mut prefs = UserPreferences::default();
+        cli.apply(&mut prefs);
+        assert_eq!(prefs.theme, "Retro");
+        assert!(!prefs.sound_enabled);
+        assert!(prefs.focus_mode);
+        assert!(prefs.typewriter_mode);
+        assert_eq!(prefs.layout.text_width, 60);
+        // The margin follows the narrower paper
+        assert_eq!(prefs.typewriter.bell_column, 60);
+    }
+}
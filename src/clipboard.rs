@@ -0,0 +1,99 @@
+/// How many cut/copied snippets the kill ring remembers
+const KILL_RING_SIZE: usize = 16;
+
+/// Internal clipboard holding the most recently cut or copied text
+#[derive(Default)]
+pub struct KillRing {
+    entries: Vec<String>,
+}
+
+impl KillRing {
+    pub fn push(&mut self, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        if self.entries.len() == KILL_RING_SIZE {
+            self.entries.remove(0);
+        }
+        self.entries.push(text);
+    }
+
+    pub fn latest(&self) -> Option<&str> {
+        self.entries.last().map(String::as_str)
+    }
+}
+
+/// Escape sequence asking the terminal to put `text` on the system clipboard (OSC 52)
+pub fn osc52_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()))
+}
+
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+        out.push(ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(ALPHABET[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            ALPHABET[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            ALPHABET[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
+/// Convert pasted line endings (CRLF or bare CR) to the `\n` used in the buffer
+pub fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Re-wrap paragraphs so no line runs past `width` columns.
+/// Paragraphs are separated by blank lines; words longer than `width` get a line of their own.
+pub fn reflow(text: &str, width: usize) -> String {
+    let width = width.max(1);
+    let paragraphs: Vec<String> = text
+        .split("\n\n")
+        .map(|paragraph| {
+            let mut lines: Vec<String> = Vec::new();
+            let mut current = String::new();
+            for word in paragraph.split_whitespace() {
+                let needed = if current.is_empty() {
+                    word.chars().count()
+                } else {
+                    current.chars().count() + 1 + word.chars().count()
+                };
+                if needed > width && !current.is_empty() {
+                    lines.push(std::mem::take(&mut current));
+                }
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(word);
+            }
+            if !current.is_empty() {
+                lines.push(current);
+            }
+            lines.join("\n")
+        })
+        .collect();
+
+    let mut out = paragraphs.join("\n\n");
+    if text.ends_with('\n') && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
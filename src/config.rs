@@ -1,10 +1,15 @@
+use crate::keymap::{self, Keymap};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Table};
 
 /// Configuration constants for the Clack typewriter application
 ///
-/// Text area and layout configuration
+/// Text area and layout configuration (`[layout]` in config.toml)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LayoutConfig {
     /// Target width for the text area in characters
     pub text_width: u16,
@@ -36,7 +41,9 @@ impl Default for LayoutConfig {
     }
 }
 
-/// Typewriter behavior configuration
+/// Typewriter behavior configuration (`[typewriter]` in config.toml)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TypewriterConfig {
     /// Column position at which the bell should ring (margin warning)
     pub bell_column: usize,
@@ -56,19 +63,107 @@ impl Default for TypewriterConfig {
     }
 }
 
+/// Autosave and crash-recovery configuration (`[autosave]` in config.toml)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutosaveConfig {
+    /// Save the document every this many seconds while it has unsaved changes (0 = off)
+    pub interval_secs: u64,
+    /// Keep a hidden `.<file>.clack-swap` recovery copy of unsaved changes
+    pub swap_file: bool,
+    /// Seconds without typing before the swap file is updated
+    pub swap_idle_secs: u64,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: 0,
+            swap_file: true,
+            swap_idle_secs: 2,
+        }
+    }
+}
+
+/// Copy kept of the previous version of a document when it is saved
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupMode {
+    /// No backup copies
+    #[default]
+    Off,
+    /// A single `file.md~` next to the document
+    Tilde,
+    /// Timestamped copies in a backup directory, oldest removed beyond `keep`
+    Timestamped,
+}
+
+/// Backup configuration (`[backup]` in config.toml)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    /// "off", "tilde" or "timestamped"
+    pub mode: BackupMode,
+    /// Where timestamped backups go; defaults to `.clack-backups` next to the document
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// How many timestamped backups of each file to keep
+    pub keep: usize,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            mode: BackupMode::Off,
+            dir: None,
+            keep: 10,
+        }
+    }
+}
+
 /// Application configuration
 #[derive(Default)]
 pub struct Config {
     pub layout: LayoutConfig,
     pub typewriter: TypewriterConfig,
+    pub autosave: AutosaveConfig,
+    pub backup: BackupConfig,
+    /// The `[keys]` table as written, kept so saving preferences doesn't drop it
+    pub keys: BTreeMap<String, String>,
+    pub keymap: Keymap,
 }
 
 impl Config {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn from_preferences(prefs: &UserPreferences) -> Self {
+        Self {
+            layout: prefs.layout.clone(),
+            typewriter: prefs.typewriter.clone(),
+            autosave: prefs.autosave.clone(),
+            backup: prefs.backup.clone(),
+            keys: prefs.keys.clone(),
+            keymap: Keymap::with_overrides(&prefs.keys),
+        }
     }
 }
 
+/// Smallest paper width that still leaves room to write
+const MIN_TEXT_WIDTH: u16 = 20;
+/// Longest allowed page feed pause; the event loop sleeps for this long
+const MAX_PAGE_FEED_PAUSE_MS: u64 = 2000;
+
+/// What happens when typing reaches the bell column
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarginMode {
+    /// Ring the bell and refuse input until Enter, like a manual typewriter's margin stop
+    #[default]
+    HardStop,
+    /// Carry the word being typed onto a new line, like an electric typewriter
+    AutoReturn,
+    /// Keep paragraphs as single lines in the file; only the display wraps
+    Soft,
+}
+
 /// User preferences for default application behavior
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserPreferences {
@@ -91,6 +186,47 @@ pub struct UserPreferences {
     /// Enable double spacing by default
     #[serde(default)]
     pub double_spacing: bool,
+
+    /// Hide Markdown markup on every line except the one being edited
+    #[serde(default)]
+    pub hide_markup: bool,
+
+    /// Write toggled settings (theme, focus, sound, ...) back to this file as they change
+    #[serde(default)]
+    pub remember_toggles: bool,
+
+    /// Margin behavior: "hard_stop", "auto_return" or "soft"
+    #[serde(default)]
+    pub margin_mode: MarginMode,
+
+    /// Re-wrap pasted paragraphs at the bell column
+    #[serde(default)]
+    pub reflow_paste: bool,
+
+    /// Copy cut/copied text to the system clipboard via the terminal (OSC 52)
+    #[serde(default = "default_true")]
+    pub osc52_clipboard: bool,
+
+    /// Paper size and padding
+    #[serde(default)]
+    pub layout: LayoutConfig,
+
+    /// Bell column, page length and feed timing
+    #[serde(default)]
+    pub typewriter: TypewriterConfig,
+
+    /// Autosave interval and recovery swap file
+    #[serde(default)]
+    pub autosave: AutosaveConfig,
+
+    /// Backup copies made when saving
+    #[serde(default)]
+    pub backup: BackupConfig,
+
+    /// Key bindings laid over the defaults: `"chord" = "action"`, or
+    /// `"chord" = "none"` to unbind a default
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keys: BTreeMap<String, String>,
 }
 
 fn default_theme() -> String {
@@ -109,6 +245,16 @@ impl Default for UserPreferences {
             focus_mode: false,
             sound_enabled: true,
             double_spacing: false,
+            hide_markup: false,
+            remember_toggles: false,
+            margin_mode: MarginMode::HardStop,
+            reflow_paste: false,
+            osc52_clipboard: true,
+            layout: LayoutConfig::default(),
+            typewriter: TypewriterConfig::default(),
+            autosave: AutosaveConfig::default(),
+            backup: BackupConfig::default(),
+            keys: BTreeMap::new(),
         }
     }
 }
@@ -116,51 +262,378 @@ impl Default for UserPreferences {
 impl UserPreferences {
     /// Parse theme string into ThemeType
     pub fn parse_theme(&self) -> crate::theme::ThemeType {
-        match self.theme.to_lowercase().as_str() {
-            "dark" => crate::theme::ThemeType::Dark,
-            "paper" | "light" => crate::theme::ThemeType::Light,
-            "retro" => crate::theme::ThemeType::Retro,
-            _ => crate::theme::ThemeType::Light, // Default to Paper
+        Self::theme_from_name(&self.theme).unwrap_or(crate::theme::ThemeType::Light)
+    }
+
+    fn theme_from_name(name: &str) -> Option<crate::theme::ThemeType> {
+        match name.to_lowercase().as_str() {
+            "dark" => Some(crate::theme::ThemeType::Dark),
+            "paper" | "light" => Some(crate::theme::ThemeType::Light),
+            "retro" => Some(crate::theme::ThemeType::Retro),
+            _ => None,
+        }
+    }
+
+    /// Load user preferences from the config file, or defaults if there is none.
+    /// Problems with the file are returned so they can be shown to the user;
+    /// the affected settings fall back to their defaults, or every setting
+    /// does if the file isn't valid TOML.
+    pub fn load() -> (Self, Vec<String>) {
+        match Self::config_path() {
+            Some(path) if path.exists() => Self::load_from(&path),
+            _ => (Self::default(), Vec::new()),
+        }
+    }
+
+    /// Load user preferences from a specific file (`--config`)
+    pub fn load_from(path: &Path) -> (Self, Vec<String>) {
+        match fs::read_to_string(path) {
+            Ok(contents) => Self::from_toml(&contents),
+            Err(e) => (
+                Self::default(),
+                vec![format!("could not read {}: {e}", path.display())],
+            ),
         }
     }
 
-    /// Load user preferences from config file, or return defaults if not found
-    pub fn load() -> Self {
-        let config_path = Self::config_path();
+    /// Parse and validate the contents of a config file. A setting with the
+    /// wrong type is reported and left at its default; the rest of the file
+    /// still applies. Only a file that isn't valid TOML is ignored entirely.
+    fn from_toml(contents: &str) -> (Self, Vec<String>) {
+        let table = match contents.parse::<toml::Table>() {
+            Ok(table) => table,
+            Err(e) => {
+                return (
+                    Self::default(),
+                    vec![format!("{}; using the default settings", e.message())],
+                )
+            }
+        };
 
-        if let Some(path) = config_path {
-            if path.exists() {
-                if let Ok(contents) = fs::read_to_string(&path) {
-                    if let Ok(prefs) = toml::from_str(&contents) {
-                        return prefs;
+        // Try each setting (each entry, inside tables) on its own against
+        // otherwise default settings, then load the ones that passed together
+        let mut problems = Vec::new();
+        let mut accepted = toml::Table::new();
+        for (key, value) in table {
+            match value {
+                toml::Value::Table(entries) => {
+                    if let Err(e) = Self::check_setting(&key, toml::Table::new().into()) {
+                        problems.push(format!("{key}: {}; using the default", e.message()));
+                        continue;
+                    }
+                    let mut section = toml::Table::new();
+                    for (name, value) in entries {
+                        let entry = toml::Table::from_iter([(name.clone(), value.clone())]);
+                        match Self::check_setting(&key, entry.into()) {
+                            Ok(()) => {
+                                section.insert(name, value);
+                            }
+                            Err(e) => problems
+                                .push(format!("{key}.{name}: {}; using the default", e.message())),
+                        }
                     }
+                    accepted.insert(key, section.into());
                 }
+                value => match Self::check_setting(&key, value.clone()) {
+                    Ok(()) => {
+                        accepted.insert(key, value);
+                    }
+                    Err(e) => problems.push(format!("{key}: {}; using the default", e.message())),
+                },
             }
         }
 
-        // Return defaults if config doesn't exist or can't be read
-        Self::default()
+        let mut prefs = Self::deserialize(toml::Value::Table(accepted)).unwrap_or_default();
+        problems.extend(prefs.validate());
+        (prefs, problems)
+    }
+
+    /// Whether settings holding only `key = value` would load
+    fn check_setting(key: &str, value: toml::Value) -> Result<(), toml::de::Error> {
+        let table = toml::Table::from_iter([(key.to_string(), value)]);
+        Self::deserialize(toml::Value::Table(table)).map(|_| ())
+    }
+
+    /// Reset out-of-range settings to their defaults, describing each one
+    pub fn validate(&mut self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let layout_defaults = LayoutConfig::default();
+        let typewriter_defaults = TypewriterConfig::default();
+
+        if Self::theme_from_name(&self.theme).is_none() {
+            problems.push(format!(
+                "unknown theme \"{}\" (expected Paper, Dark or Retro)",
+                self.theme
+            ));
+            self.theme = default_theme();
+        }
+
+        if self.layout.text_width < MIN_TEXT_WIDTH {
+            problems.push(format!(
+                "layout.text_width must be at least {MIN_TEXT_WIDTH}; using {}",
+                layout_defaults.text_width
+            ));
+            self.layout.text_width = layout_defaults.text_width;
+        }
+
+        let text_width = self.layout.text_width as usize;
+        if self.typewriter.bell_column == 0 || self.typewriter.bell_column > text_width {
+            let fallback = typewriter_defaults.bell_column.min(text_width);
+            problems.push(format!(
+                "typewriter.bell_column must be between 1 and layout.text_width ({text_width}); using {fallback}"
+            ));
+            self.typewriter.bell_column = fallback;
+        }
+
+        if self.typewriter.lines_per_page == 0 {
+            problems.push(format!(
+                "typewriter.lines_per_page must be at least 1; using {}",
+                typewriter_defaults.lines_per_page
+            ));
+            self.typewriter.lines_per_page = typewriter_defaults.lines_per_page;
+        }
+
+        if self.typewriter.page_feed_pause_ms > MAX_PAGE_FEED_PAUSE_MS {
+            problems.push(format!(
+                "typewriter.page_feed_pause_ms must be at most {MAX_PAGE_FEED_PAUSE_MS}; using {}",
+                typewriter_defaults.page_feed_pause_ms
+            ));
+            self.typewriter.page_feed_pause_ms = typewriter_defaults.page_feed_pause_ms;
+        }
+
+        if self.backup.mode == BackupMode::Timestamped && self.backup.keep == 0 {
+            problems.push(format!(
+                "backup.keep must be at least 1; using {}",
+                BackupConfig::default().keep
+            ));
+            self.backup.keep = BackupConfig::default().keep;
+        }
+
+        self.keys
+            .retain(|chord, action| match keymap::parse_binding(chord, action) {
+                Ok(_) => true,
+                Err(problem) => {
+                    problems.push(format!("keys: {problem}; ignoring \"{chord}\""));
+                    false
+                }
+            });
+
+        problems
     }
 
     /// Get the config file path: ~/.config/clack/config.toml
-    fn config_path() -> Option<PathBuf> {
+    pub fn config_path() -> Option<PathBuf> {
         let home = std::env::var("HOME").ok()?;
         let config_dir = PathBuf::from(home).join(".config").join("clack");
         Some(config_dir.join("config.toml"))
     }
 
-    /// Save current preferences to config file
-    #[allow(dead_code)]
-    pub fn save(&self) -> std::io::Result<()> {
-        if let Some(path) = Self::config_path() {
-            // Create config directory if it doesn't exist
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
+    /// Save current preferences to a config file. Comments, formatting and
+    /// keys Clack doesn't know about are kept.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        self.write_keys(path, None)
+    }
+
+    /// Like `save`, but only update the given top-level keys
+    pub fn save_keys(&self, path: &Path, keys: &[&str]) -> std::io::Result<()> {
+        self.write_keys(path, Some(keys))
+    }
+
+    fn write_keys(&self, path: &Path, keys: Option<&[&str]>) -> std::io::Result<()> {
+        let mut doc = match fs::read_to_string(path) {
+            // Refuse to overwrite a file we can't parse rather than lose its contents
+            Ok(contents) => contents
+                .parse::<DocumentMut>()
+                .map_err(std::io::Error::other)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => DocumentMut::new(),
+            Err(e) => return Err(e),
+        };
+
+        let fresh = toml::to_string_pretty(self)
+            .map_err(std::io::Error::other)?
+            .parse::<DocumentMut>()
+            .map_err(std::io::Error::other)?;
+        for (key, item) in fresh.iter() {
+            if keys.is_some_and(|keys| !keys.contains(&key)) {
+                continue;
             }
+            merge_item(doc.as_table_mut(), key, item.clone());
+        }
+
+        // Create config directory if it doesn't exist
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, doc.to_string())
+    }
+}
 
-            let toml_string = toml::to_string_pretty(self).map_err(std::io::Error::other)?;
-            fs::write(&path, toml_string)?;
+/// Set `key` in `table` to `new`, recursing into sub-tables and keeping the
+/// existing entry's comments
+fn merge_item(table: &mut Table, key: &str, new: Item) {
+    match (table.get_mut(key), new) {
+        (Some(Item::Table(existing)), Item::Table(new_table)) => {
+            for (k, v) in new_table.iter() {
+                merge_item(existing, k, v.clone());
+            }
         }
-        Ok(())
+        (Some(Item::Value(existing)), Item::Value(mut new_value)) => {
+            *new_value.decor_mut() = existing.decor().clone();
+            *existing = new_value;
+        }
+        (_, new) => {
+            table.insert(key, new);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+
+    #[test]
+    fn test_layout_and_typewriter_tables_are_loaded() {
+        let (prefs, problems) = UserPreferences::from_toml(
+            r#"
+            theme = "Retro"
+
+            [layout]
+            text_width = 64
+            fancy_borders = false
+
+            [typewriter]
+            bell_column = 60
+            lines_per_page = 30
+            "#,
+        );
+        assert!(problems.is_empty());
+        assert_eq!(prefs.layout.text_width, 64);
+        assert!(!prefs.layout.fancy_borders);
+        assert_eq!(prefs.layout.pad_left, 2); // unspecified keys keep defaults
+        assert_eq!(prefs.typewriter.bell_column, 60);
+        assert_eq!(prefs.typewriter.lines_per_page, 30);
+        assert_eq!(prefs.typewriter.page_feed_pause_ms, 350);
+    }
+
+    #[test]
+    fn test_invalid_values_are_reported_and_reset() {
+        let (prefs, problems) = UserPreferences::from_toml(
+            r#"
+            theme = "Sepia"
+
+            [layout]
+            text_width = 40
+
+            [typewriter]
+            bell_column = 72
+            lines_per_page = 0
+            "#,
+        );
+        assert_eq!(problems.len(), 3);
+        assert_eq!(prefs.theme, "Paper");
+        assert_eq!(prefs.typewriter.bell_column, 40);
+        assert_eq!(prefs.typewriter.lines_per_page, 54);
+    }
+
+    #[test]
+    fn test_keys_table_is_checked_and_applied() {
+        let (prefs, problems) = UserPreferences::from_toml(
+            r#"
+            [keys]
+            f2 = "none"
+            "alt+f" = "toggle_focus"
+            "ctrl+k" = "explode"
+            "#,
+        );
+        assert_eq!(
+            problems,
+            ["keys: unknown action \"explode\"; ignoring \"ctrl+k\""]
+        );
+        assert_eq!(prefs.keys.len(), 2);
+
+        let keymap = Config::from_preferences(&prefs).keymap;
+        assert_eq!(keymap.label(crate::keymap::Action::ToggleFocus), "Alt+F");
+    }
+
+    #[test]
+    fn test_malformed_file_reports_parse_error() {
+        let (prefs, problems) = UserPreferences::from_toml("theme = \"Retro\"\n[layout\n");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(prefs.theme, "Paper");
+    }
+
+    #[test]
+    fn test_wrongly_typed_setting_only_resets_itself() {
+        let (prefs, problems) = UserPreferences::from_toml(
+            r#"
+            theme = "Retro"
+            focus_mode = "yes"
+
+            [layout]
+            text_width = "wide"
+            pad_left = 4
+
+            [keys]
+            f2 = 1
+            "alt+f" = "toggle_focus"
+            "#,
+        );
+        assert_eq!(problems.len(), 3, "{problems:?}");
+        assert!(problems[0].starts_with("focus_mode: "));
+        assert_eq!(prefs.theme, "Retro");
+        assert!(!prefs.focus_mode);
+        assert_eq!(prefs.layout.text_width, 80);
+        assert_eq!(prefs.layout.pad_left, 4);
+        assert_eq!(prefs.keys.len(), 1);
+    }
+
+    #[test]
+    fn test_save_keeps_comments_and_unknown_keys() {
+        let dir = scratch("clack_test_save_prefs");
+        let path = dir.join("config.toml");
+        fs::write(
+            &path,
+            "# My settings\ntheme = \"Dark\" # night owl\nfuture_option = 3\n\n[layout]\n# narrow paper\ntext_width = 64\n",
+        )
+        .unwrap();
+
+        let (mut prefs, _) = UserPreferences::load_from(&path);
+        prefs.theme = "Retro".to_string();
+        prefs.focus_mode = true;
+        prefs.save(&path).unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("# My settings"));
+        assert!(saved.contains("theme = \"Retro\" # night owl"));
+        assert!(saved.contains("future_option = 3"));
+        assert!(saved.contains("# narrow paper\ntext_width = 64"));
+        assert!(saved.contains("focus_mode = true"));
+
+        let (reloaded, problems) = UserPreferences::load_from(&path);
+        assert!(problems.is_empty());
+        assert_eq!(reloaded.theme, "Retro");
+        assert!(reloaded.focus_mode);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_save_keys_only_touches_named_keys() {
+        let dir = scratch("clack_test_save_keys");
+        let path = dir.join("config.toml");
+        fs::write(&path, "sound_enabled = true\n").unwrap();
+
+        let prefs = UserPreferences {
+            sound_enabled: false,
+            focus_mode: true,
+            ..UserPreferences::default()
+        };
+        prefs.save_keys(&path, &["focus_mode"]).unwrap();
+
+        let saved = fs::read_to_string(&path).unwrap();
+        assert_eq!(saved, "sound_enabled = true\nfocus_mode = true\n");
+
+        fs::remove_dir_all(&dir).ok();
     }
 }
@@ -0,0 +1,246 @@
+use crate::buffer::CloseScope;
+use crate::encoding::{self, TextFormat};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Largest diff (lines on one side times lines on the other, after trimming
+/// the common start and end) worked out line by line; anything bigger is shown
+/// as the whole middle removed and re-added
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// What a file on disk looked like when it was last loaded or saved, so a
+/// change made by another program can be noticed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl Fingerprint {
+    /// Fingerprint of `bytes`, just read from a file with metadata `meta`
+    pub fn new(bytes: &[u8], meta: &fs::Metadata) -> Self {
+        Self {
+            modified: meta.modified().ok(),
+            len: meta.len(),
+            hash: hash_bytes(bytes),
+        }
+    }
+
+    /// Fingerprint of the file at `path`, or None if there is no file there
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        let meta = match fs::metadata(path) {
+            Ok(meta) => meta,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let bytes = fs::read(path)?;
+        Ok(Some(Self::new(&bytes, &meta)))
+    }
+}
+
+/// Read and decode a document, fingerprinting exactly the bytes that were read
+pub fn read_text(path: &Path) -> io::Result<(String, TextFormat, Fingerprint)> {
+    let meta = fs::metadata(path)?;
+    let bytes = fs::read(path)?;
+    let (text, format) = encoding::decode(&bytes);
+    Ok((text, format, Fingerprint::new(&bytes, &meta)))
+}
+
+/// How the file on disk compares with a fingerprint taken earlier
+#[derive(Debug, PartialEq)]
+pub enum DiskStatus {
+    Unchanged,
+    /// Timestamp or size moved but the contents are the same (touched, or
+    /// rewritten with identical text); carries the fresh fingerprint
+    Touched(Fingerprint),
+    /// Another program changed the contents
+    Changed(Fingerprint),
+    /// The file has been deleted or moved away
+    Missing,
+}
+
+/// Compare the file at `path` with `recorded`. Only the metadata is read
+/// unless it has moved, so this is cheap enough to call every few seconds.
+pub fn check(path: &Path, recorded: &Fingerprint) -> io::Result<DiskStatus> {
+    let meta = match fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(DiskStatus::Missing),
+        Err(e) => return Err(e),
+    };
+    if meta.modified().ok() == recorded.modified && meta.len() == recorded.len {
+        return Ok(DiskStatus::Unchanged);
+    }
+    let current = Fingerprint::new(&fs::read(path)?, &meta);
+    Ok(if current.hash == recorded.hash {
+        DiskStatus::Touched(current)
+    } else {
+        DiskStatus::Changed(current)
+    })
+}
+
+/// State of the changed-on-disk prompt while it is open
+pub struct ExternalChange {
+    /// The version on disk that the prompt is about
+    pub disk: Fingerprint,
+    /// Buffer-to-disk comparison, once the writer asks to see it
+    pub diff: Option<Vec<DiffLine>>,
+    /// First diff line shown
+    pub scroll: usize,
+    /// The prompt interrupted a save from the unsaved-changes prompt; carry
+    /// on closing once the conflict is settled by overwriting
+    pub then_close: Option<CloseScope>,
+}
+
+impl ExternalChange {
+    pub fn new(disk: Fingerprint) -> Self {
+        Self {
+            disk,
+            diff: None,
+            scroll: 0,
+            then_close: None,
+        }
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// One line of a line-by-line comparison
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    /// Only in the buffer
+    Removed(String),
+    /// Only on disk
+    Added(String),
+}
+
+/// Line diff turning `ours` into `theirs`, via the longest common subsequence
+pub fn diff_lines(ours: &str, theirs: &str) -> Vec<DiffLine> {
+    let a: Vec<&str> = ours.lines().collect();
+    let b: Vec<&str> = theirs.lines().collect();
+
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut out: Vec<DiffLine> = a[..prefix]
+        .iter()
+        .map(|l| DiffLine::Same(l.to_string()))
+        .collect();
+
+    if a_mid.len() * b_mid.len() > MAX_DIFF_CELLS {
+        out.extend(a_mid.iter().map(|l| DiffLine::Removed(l.to_string())));
+        out.extend(b_mid.iter().map(|l| DiffLine::Added(l.to_string())));
+    } else {
+        // lcs[i][j]: length of the common subsequence of a_mid[i..] and b_mid[j..]
+        let (n, m) = (a_mid.len(), b_mid.len());
+        let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if a_mid[i] == b_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && a_mid[i] == b_mid[j] {
+                out.push(DiffLine::Same(a_mid[i].to_string()));
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                out.push(DiffLine::Removed(a_mid[i].to_string()));
+                i += 1;
+            } else {
+                out.push(DiffLine::Added(b_mid[j].to_string()));
+                j += 1;
+            }
+        }
+    }
+
+    out.extend(
+        a[a.len() - suffix..]
+            .iter()
+            .map(|l| DiffLine::Same(l.to_string())),
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+
+    #[test]
+    fn test_check_notices_changes() {
+        let dir = scratch("clack_test_disk_check");
+        let path = dir.join("draft.md");
+        fs::write(&path, "first draft").unwrap();
+        let recorded = Fingerprint::read(&path).unwrap().unwrap();
+        assert_eq!(check(&path, &recorded).unwrap(), DiskStatus::Unchanged);
+
+        fs::write(&path, "second draft, longer").unwrap();
+        assert!(matches!(
+            check(&path, &recorded).unwrap(),
+            DiskStatus::Changed(_)
+        ));
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(check(&path, &recorded).unwrap(), DiskStatus::Missing);
+        assert_eq!(Fingerprint::read(&path).unwrap(), None);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_identical_rewrite_is_only_touched() {
+        let dir = scratch("clack_test_disk_touch");
+        let path = dir.join("draft.md");
+        fs::write(&path, "same text").unwrap();
+        let mut recorded = Fingerprint::read(&path).unwrap().unwrap();
+        // Pretend it was recorded at another time, as after a `touch`
+        recorded.modified = Some(SystemTime::UNIX_EPOCH);
+
+        assert!(matches!(
+            check(&path, &recorded).unwrap(),
+            DiskStatus::Touched(_)
+        ));
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let diff = diff_lines("one\ntwo\nthree\nfour\n", "one\n2\nthree\nfour\nfive\n");
+        assert_eq!(
+            diff,
+            vec![
+                DiffLine::Same("one".to_string()),
+                DiffLine::Removed("two".to_string()),
+                DiffLine::Added("2".to_string()),
+                DiffLine::Same("three".to_string()),
+                DiffLine::Same("four".to_string()),
+                DiffLine::Added("five".to_string()),
+            ]
+        );
+        assert!(diff_lines("same\n", "same\n")
+            .iter()
+            .all(|l| matches!(l, DiffLine::Same(_))));
+    }
+}
@@ -0,0 +1,215 @@
+use ropey::Rope;
+use std::fmt;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Characters for bytes 0x80-0x9F in Windows-1252. The five bytes it leaves
+/// undefined decode to the matching C1 control, as in Latin-1, so any byte
+/// sequence survives a load and save unchanged.
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// How lines end in the file on disk. The buffer itself always uses `\n`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+/// Character encoding of the file on disk
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// Windows-1252, which also reads Latin-1 (ISO-8859-1) files
+    Windows1252,
+}
+
+/// Everything about a file's bytes that isn't its text, remembered on load
+/// so a save writes the file back the way it came
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextFormat {
+    pub encoding: Encoding,
+    /// Starts with a UTF-8 byte order mark
+    pub bom: bool,
+    pub line_ending: LineEnding,
+}
+
+impl fmt::Display for TextFormat {
+    /// Short form for the footer, e.g. `UTF-8 LF` or `CP1252 CRLF`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let encoding = match (self.encoding, self.bom) {
+            (Encoding::Utf8, false) => "UTF-8",
+            (Encoding::Utf8, true) => "UTF-8 BOM",
+            (Encoding::Windows1252, _) => "CP1252",
+        };
+        let line_ending = match self.line_ending {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+        };
+        write!(f, "{encoding} {line_ending}")
+    }
+}
+
+/// A character that the file's encoding has no byte for
+#[derive(Debug, PartialEq)]
+pub struct Unencodable {
+    pub ch: char,
+    /// 1-based line of the first occurrence
+    pub line: usize,
+}
+
+impl fmt::Display for Unencodable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "'{}' on line {} can't be written as Windows-1252",
+            self.ch, self.line
+        )
+    }
+}
+
+impl std::error::Error for Unencodable {}
+
+/// Decode a file's bytes: strip a UTF-8 byte order mark, fall back to
+/// Windows-1252 when the bytes aren't valid UTF-8, and normalise line endings
+/// to `\n`. Files where most line breaks are `\r\n` are remembered as CRLF.
+pub fn decode(bytes: &[u8]) -> (String, TextFormat) {
+    let (bytes, bom) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(rest) => (rest, true),
+        None => (bytes, false),
+    };
+    let (text, encoding) = match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), Encoding::Utf8),
+        Err(_) => (
+            bytes.iter().map(|&b| decode_cp1252(b)).collect(),
+            Encoding::Windows1252,
+        ),
+    };
+
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    let line_ending = if crlf > lf {
+        LineEnding::Crlf
+    } else {
+        LineEnding::Lf
+    };
+    let text = if crlf > 0 {
+        text.replace("\r\n", "\n")
+    } else {
+        text
+    };
+
+    (
+        text,
+        TextFormat {
+            encoding,
+            bom: bom && encoding == Encoding::Utf8,
+            line_ending,
+        },
+    )
+}
+
+/// The bytes to write for `text` in `format`
+pub fn encode(text: &Rope, format: TextFormat) -> Result<Vec<u8>, Unencodable> {
+    let mut out = Vec::with_capacity(text.len_bytes() + 3);
+    if format.bom && format.encoding == Encoding::Utf8 {
+        out.extend_from_slice(UTF8_BOM);
+    }
+    let mut line = 1;
+    for ch in text.chars() {
+        if ch == '\n' {
+            if format.line_ending == LineEnding::Crlf {
+                out.push(b'\r');
+            }
+            out.push(b'\n');
+            line += 1;
+            continue;
+        }
+        match format.encoding {
+            Encoding::Utf8 => {
+                let mut buf = [0; 4];
+                out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+            }
+            Encoding::Windows1252 => {
+                out.push(encode_cp1252(ch).ok_or(Unencodable { ch, line })?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+fn decode_cp1252(byte: u8) -> char {
+    match byte {
+        0x80..=0x9F => CP1252_HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+fn encode_cp1252(ch: char) -> Option<u8> {
+    match u32::from(ch) {
+        code @ (0..=0x7F | 0xA0..=0xFF) => Some(code as u8),
+        _ => CP1252_HIGH
+            .iter()
+            .position(|&c| c == ch)
+            .map(|i| 0x80 + i as u8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crlf_with_bom_round_trips() {
+        let bytes = b"\xEF\xBB\xBFDear Sir,\r\nThank you.\r\n";
+        let (text, format) = decode(bytes);
+        assert_eq!(text, "Dear Sir,\nThank you.\n");
+        assert_eq!(
+            format,
+            TextFormat {
+                encoding: Encoding::Utf8,
+                bom: true,
+                line_ending: LineEnding::Crlf,
+            }
+        );
+        assert_eq!(format.to_string(), "UTF-8 BOM CRLF");
+
+        // New lines typed into the buffer get the file's ending too
+        let mut rope = Rope::from_str(&text);
+        rope.insert(rope.len_chars(), "P.S.\n");
+        assert_eq!(
+            encode(&rope, format).unwrap(),
+            b"\xEF\xBB\xBFDear Sir,\r\nThank you.\r\nP.S.\r\n"
+        );
+    }
+
+    #[test]
+    fn test_windows_1252_round_trips() {
+        let bytes = b"caf\xE9 \x93quoted\x94 \x81\n";
+        let (text, format) = decode(bytes);
+        assert_eq!(text, "café “quoted” \u{81}\n");
+        assert_eq!(format.encoding, Encoding::Windows1252);
+        assert_eq!(encode(&Rope::from_str(&text), format).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_unencodable_character_is_reported() {
+        let format = TextFormat {
+            encoding: Encoding::Windows1252,
+            ..TextFormat::default()
+        };
+        let err = encode(&Rope::from_str("fine\nsnow ☃\n"), format).unwrap_err();
+        assert_eq!(err, Unencodable { ch: '☃', line: 2 });
+    }
+
+    #[test]
+    fn test_mostly_lf_file_stays_lf() {
+        let (text, format) = decode(b"one\ntwo\r\nthree\n");
+        assert_eq!(text, "one\ntwo\nthree\n");
+        assert_eq!(format.line_ending, LineEnding::Lf);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::app::{App, ExternalChoice, QuitChoice};
+use crate::keymap::Action;
+use crate::minibuffer::FilePrompt;
+use crate::search::{SearchMode, SearchToggle};
+use crate::sound::Sound;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+/// Act on a key press, sending it to whichever prompt is open or else to the
+/// editor. Returns true if the application should exit.
+pub fn handle_key(app: &mut App, key: KeyEvent) -> bool {
+    // Recovering a previous session comes before anything else
+    if app.recovery_prompt.is_some() {
+        handle_recovery_key(app, key);
+        return false;
+    }
+    // Then settling a file that another program changed
+    if app.external_change.is_some() {
+        return handle_external_key(app, key);
+    }
+    // The quit prompt takes every key until it is answered
+    if app.quit_prompt.is_some() {
+        return handle_quit_key(app, key);
+    }
+    if app.config.keymap.action(&key) == Some(Action::Quit) {
+        return app.request_quit();
+    }
+    if app.navigator.is_some() {
+        handle_navigator_key(app, key);
+        return false;
+    }
+    if app.format_prompt {
+        handle_format_key(app, key);
+        return false;
+    }
+    // Save As / Open prompts receive all keys while open
+    if app.file_prompt.is_some() {
+        return handle_file_prompt_key(app, key);
+    }
+    // While the Find/Replace prompt is open it receives all keys
+    if app.search.is_some() {
+        handle_search_key(app, key);
+        return false;
+    }
+    // The help overlay scrolls when it doesn't fit; other keys go on to the editor
+    if app.show_help && scroll_help(app, key) {
+        return false;
+    }
+
+    if let Some((action, extend)) = app.config.keymap.resolve(&key) {
+        run_action(app, action, extend);
+        return false;
+    }
+
+    // Shift turns any cursor motion below into a selection
+    let extend = key.modifiers.contains(KeyModifiers::SHIFT);
+    // An unbound Ctrl or Alt chord does nothing rather than typing its
+    // letter. Both at once is how AltGr characters arrive on Windows.
+    let alt_gr = key
+        .modifiers
+        .contains(KeyModifiers::CONTROL | KeyModifiers::ALT);
+    let types = alt_gr
+        || !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+    match key.code {
+        // Esc only dismisses things; it never quits
+        KeyCode::Esc => {
+            if app.show_help {
+                app.toggle_help();
+            } else {
+                app.selection_anchor = None;
+            }
+        }
+        KeyCode::Enter => {
+            app.clear_status();
+            app.enter_key();
+            // Check if we crossed a page boundary and need to pause for feed sound
+            if app.check_and_play_page_feed() {
+                // Brief pause to let the mechanical "feed" action feel real
+                std::thread::sleep(std::time::Duration::from_millis(
+                    app.config.typewriter.page_feed_pause_ms,
+                ));
+            }
+        }
+        KeyCode::Char(c) if types => {
+            app.clear_status();
+            app.insert_char(c);
+            // Check if we crossed a page boundary while typing
+            if app.check_and_play_page_feed() {
+                std::thread::sleep(std::time::Duration::from_millis(
+                    app.config.typewriter.page_feed_pause_ms,
+                ));
+            }
+        }
+        KeyCode::Backspace => {
+            app.clear_status();
+            app.delete_char();
+        }
+        KeyCode::Delete => {
+            app.clear_status();
+            app.delete_char_forward();
+        }
+
+        // Simple Navigation
+        KeyCode::Left => {
+            app.prepare_motion(extend);
+            if app.doc.cursor > 0 {
+                app.doc.cursor -= 1;
+            }
+        }
+        KeyCode::Right => {
+            app.prepare_motion(extend);
+            if app.doc.cursor < app.doc.content.len_chars() {
+                app.doc.cursor += 1;
+            }
+        }
+        // Up/Down follow wrapped rows; the line_up/line_down actions jump whole lines
+        KeyCode::Up => {
+            app.prepare_motion(extend);
+            app.move_visual_up();
+        }
+        KeyCode::Down => {
+            app.prepare_motion(extend);
+            let crossed_page = app.move_visual_down();
+            feed_pause(app, crossed_page);
+        }
+
+        _ => {}
+    }
+    false
+}
+
+/// Carry out a bound action. Motions select as they go when `extend` is set.
+fn run_action(app: &mut App, action: Action, extend: bool) {
+    if action.is_motion() {
+        app.prepare_motion(extend);
+    } else if !matches!(
+        action,
+        Action::Help
+            | Action::ToggleFocus
+            | Action::ToggleTypewriter
+            | Action::ToggleSound
+            | Action::CycleTheme
+            | Action::ToggleDoubleSpacing
+            | Action::ToggleHideMarkup
+            | Action::Save
+            | Action::SavePreferences
+    ) {
+        app.clear_status();
+    }
+
+    match action {
+        Action::Help => app.toggle_help(),
+        Action::ToggleFocus => app.toggle_focus(),
+        Action::ToggleTypewriter => app.toggle_mode(),
+        Action::ToggleSound => app.toggle_sound(),
+        Action::CycleTheme => app.cycle_theme(),
+        Action::ToggleDoubleSpacing => app.toggle_double_spacing(),
+        Action::ToggleHideMarkup => app.toggle_hide_markup(),
+        Action::Save => {
+            if app.file_path.is_none() {
+                app.open_save_as(None);
+            } else if let Err(e) = app.save_to_file() {
+                app.set_error(format!("Failed to save: {e}"));
+            }
+        }
+        Action::SaveAs => app.open_save_as(None),
+        Action::Open => app.open_file_picker(),
+        Action::NewBuffer => app.new_buffer(),
+        Action::CloseBuffer => app.request_close_buffer(),
+        Action::PrevBuffer => app.cycle_buffer(false),
+        Action::NextBuffer => app.cycle_buffer(true),
+        Action::Navigator => app.toggle_navigator(),
+        Action::FileFormat => app.toggle_format_prompt(),
+        Action::SavePreferences => app.save_preferences(),
+        Action::Undo => app.undo(),
+        Action::Redo => app.redo(),
+        Action::Cut => app.cut_selection(),
+        Action::Copy => {
+            app.copy_selection();
+        }
+        Action::Paste => app.paste_from_kill_ring(),
+        Action::Find => app.open_search(SearchMode::Find),
+        Action::Replace => app.open_search(SearchMode::Replace),
+        Action::WordLeft => app.move_word_left(),
+        Action::WordRight => app.move_word_right(),
+        Action::LineUp => app.move_cursor_up(),
+        Action::LineDown => {
+            let crossed_page = app.move_cursor_down();
+            feed_pause(app, crossed_page);
+        }
+        Action::LineStart => app.move_to_line_start(),
+        Action::LineEnd => app.move_to_line_end(),
+        // Handled before any prompt gets the key
+        Action::Quit => {}
+    }
+}
+
+/// Play the feed sound and pause after the cursor moved onto a new page
+fn feed_pause(app: &mut App, crossed_page: bool) {
+    if crossed_page && app.sound_enabled {
+        app.audio.trigger(Sound::Feed);
+        std::thread::sleep(std::time::Duration::from_millis(
+            app.config.typewriter.page_feed_pause_ms,
+        ));
+    }
+}
+
+/// Scroll the help overlay; returns false for keys that don't scroll it
+fn scroll_help(app: &mut App, key: KeyEvent) -> bool {
+    if !key.modifiers.is_empty() {
+        return false;
+    }
+    app.help_scroll = match key.code {
+        KeyCode::Up => app.help_scroll.saturating_sub(1),
+        KeyCode::Down => app.help_scroll + 1,
+        KeyCode::PageUp => app.help_scroll.saturating_sub(10),
+        KeyCode::PageDown => app.help_scroll + 10,
+        _ => return false,
+    };
+    true
+}
+
+/// Clicks place the cursor; dragging selects
+pub fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            let extend = mouse.modifiers.contains(KeyModifiers::SHIFT);
+            app.click_at(mouse.column, mouse.row, extend);
+        }
+        // Dragging extends the selection from where the button went down
+        MouseEventKind::Drag(MouseButton::Left) => app.click_at(mouse.column, mouse.row, true),
+        _ => {}
+    }
+}
+
+/// Answer the quit prompt; returns true if the application should exit
+fn handle_quit_key(app: &mut App, key: KeyEvent) -> bool {
+    let choice = match key.code {
+        KeyCode::Char('s' | 'S') | KeyCode::Enter => QuitChoice::Save,
+        KeyCode::Char('d' | 'D') => QuitChoice::Discard,
+        KeyCode::Char('c' | 'C') | KeyCode::Esc => QuitChoice::Cancel,
+        _ => return false,
+    };
+    app.resolve_quit(choice)
+}
+
+/// Keys for the Save As prompt and Open picker; returns true if the application should exit
+fn handle_file_prompt_key(app: &mut App, key: KeyEvent) -> bool {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let confirming = match &app.file_prompt {
+        Some(FilePrompt::SaveAs(state)) => state.confirm_overwrite,
+        Some(FilePrompt::Open(_)) | None => false,
+    };
+
+    match key.code {
+        KeyCode::Esc if confirming => app.file_prompt_decline(),
+        KeyCode::Char('n' | 'N') if confirming => app.file_prompt_decline(),
+        KeyCode::Char('y' | 'Y') if confirming => return app.file_prompt_confirm(),
+        KeyCode::Esc => app.close_file_prompt(),
+        KeyCode::Enter => return app.file_prompt_confirm(),
+        KeyCode::Tab => app.file_prompt_complete(),
+        KeyCode::Up => app.file_prompt_move(-1),
+        KeyCode::Down => app.file_prompt_move(1),
+        KeyCode::Backspace => app.file_prompt_backspace(),
+        KeyCode::Char(c) if !ctrl => app.file_prompt_input(c),
+        _ => {}
+    }
+    false
+}
+
+fn handle_navigator_key(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc => app.toggle_navigator(),
+        _ if app.config.keymap.action(&key) == Some(Action::Navigator) => app.toggle_navigator(),
+        KeyCode::Up => app.navigator_move(-1),
+        KeyCode::Down => app.navigator_move(1),
+        KeyCode::Enter => app.navigator_open(),
+        KeyCode::Char('c' | 'C') => {
+            app.navigator = None;
+            app.compile_project();
+        }
+        _ => {}
+    }
+}
+
+fn handle_recovery_key(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Char('r' | 'R') | KeyCode::Enter => app.resolve_recovery(true),
+        KeyCode::Char('d' | 'D') => app.resolve_recovery(false),
+        _ => {}
+    }
+}
+
+/// Keys for the file format (line endings / encoding) prompt
+fn handle_format_key(app: &mut App, key: KeyEvent) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc | KeyCode::Enter => app.toggle_format_prompt(),
+        _ if app.config.keymap.action(&key) == Some(Action::FileFormat) => {
+            app.toggle_format_prompt()
+        }
+        KeyCode::Char('l' | 'L') if !ctrl => app.convert_line_endings(),
+        KeyCode::Char('e' | 'E') if !ctrl => app.convert_encoding(),
+        KeyCode::Char('b' | 'B') if !ctrl => app.toggle_bom(),
+        _ => {}
+    }
+}
+
+/// Keys for the changed-on-disk prompt; returns true if the application should exit
+fn handle_external_key(app: &mut App, key: KeyEvent) -> bool {
+    let choice = match key.code {
+        KeyCode::Char('r' | 'R') => ExternalChoice::Reload,
+        KeyCode::Char('o' | 'O') => ExternalChoice::Overwrite,
+        KeyCode::Char('c' | 'C') | KeyCode::Esc => ExternalChoice::Cancel,
+        KeyCode::Char('d' | 'D') => {
+            app.toggle_external_diff();
+            return false;
+        }
+        KeyCode::Up => {
+            app.scroll_external_diff(-1);
+            return false;
+        }
+        KeyCode::Down => {
+            app.scroll_external_diff(1);
+            return false;
+        }
+        KeyCode::PageUp => {
+            app.scroll_external_diff(-10);
+            return false;
+        }
+        KeyCode::PageDown => {
+            app.scroll_external_diff(10);
+            return false;
+        }
+        _ => return false,
+    };
+    app.resolve_external(choice)
+}
+
+fn handle_search_key(app: &mut App, key: KeyEvent) {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    let action = app.config.keymap.action(&key);
+    let replacing = app
+        .search
+        .as_ref()
+        .is_some_and(|s| s.mode == SearchMode::Replace);
+
+    match key.code {
+        KeyCode::Esc => app.close_search(),
+        KeyCode::Enter if replacing => app.replace_current(),
+        KeyCode::Enter | KeyCode::Down => app.search_next(),
+        KeyCode::Up => app.search_prev(),
+        KeyCode::Tab => app.search_switch_field(),
+        KeyCode::Backspace => app.search_backspace(),
+        _ if action == Some(Action::Find) => app.search_next(),
+        _ if action == Some(Action::Replace) => app.open_search(SearchMode::Replace),
+        KeyCode::Char('c') if alt => app.toggle_search_option(SearchToggle::CaseSensitive),
+        KeyCode::Char('w') if alt => app.toggle_search_option(SearchToggle::WholeWord),
+        KeyCode::Char('r') if alt => app.toggle_search_option(SearchToggle::Regex),
+        KeyCode::Char('a') if alt && replacing => app.replace_all(),
+        KeyCode::Char(c) if !ctrl && !alt => app.search_input(c),
+        _ => {}
+    }
+}
@@ -0,0 +1,583 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A command that can be bound to a key in the `[keys]` table of config.toml.
+/// Typing, Enter, Backspace, Delete, Esc and the plain arrow keys are not
+/// actions; they always do what they say.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Help,
+    ToggleFocus,
+    ToggleTypewriter,
+    ToggleSound,
+    CycleTheme,
+    ToggleDoubleSpacing,
+    ToggleHideMarkup,
+    Save,
+    SaveAs,
+    Open,
+    NewBuffer,
+    CloseBuffer,
+    PrevBuffer,
+    NextBuffer,
+    Navigator,
+    FileFormat,
+    SavePreferences,
+    Undo,
+    Redo,
+    Cut,
+    Copy,
+    Paste,
+    Find,
+    Replace,
+    WordLeft,
+    WordRight,
+    LineUp,
+    LineDown,
+    LineStart,
+    LineEnd,
+    Quit,
+}
+
+/// Every action with its name in config.toml and its line in the help
+/// overlay, in the order the overlay lists them
+const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Help, "help", "Toggle this help menu"),
+    (
+        Action::ToggleFocus,
+        "toggle_focus",
+        "Toggle focus mode (dim inactive lines)",
+    ),
+    (
+        Action::ToggleTypewriter,
+        "toggle_typewriter",
+        "Toggle typewriter mode (center line)",
+    ),
+    (Action::ToggleSound, "toggle_sound", "Toggle sound effects"),
+    (
+        Action::CycleTheme,
+        "cycle_theme",
+        "Cycle theme (Light/Dark/Retro)",
+    ),
+    (
+        Action::ToggleDoubleSpacing,
+        "toggle_double_spacing",
+        "Toggle double spacing",
+    ),
+    (
+        Action::ToggleHideMarkup,
+        "toggle_hide_markup",
+        "Hide markup except on the current line",
+    ),
+    (Action::Save, "save", "Save file"),
+    (Action::SaveAs, "save_as", "Save as"),
+    (Action::Open, "open", "Open a file"),
+    (Action::NewBuffer, "new_buffer", "New buffer"),
+    (Action::CloseBuffer, "close_buffer", "Close buffer"),
+    (Action::PrevBuffer, "prev_buffer", "Previous buffer"),
+    (Action::NextBuffer, "next_buffer", "Next buffer"),
+    (
+        Action::Navigator,
+        "navigator",
+        "Chapter navigator and compile (projects)",
+    ),
+    (
+        Action::FileFormat,
+        "file_format",
+        "Line endings and encoding",
+    ),
+    (
+        Action::SavePreferences,
+        "save_preferences",
+        "Save toggled settings to config.toml",
+    ),
+    (Action::Undo, "undo", "Undo"),
+    (Action::Redo, "redo", "Redo"),
+    (Action::Cut, "cut", "Cut"),
+    (Action::Copy, "copy", "Copy"),
+    (Action::Paste, "paste", "Paste"),
+    (Action::Find, "find", "Find"),
+    (Action::Replace, "replace", "Find and replace"),
+    (Action::WordLeft, "word_left", "Previous word"),
+    (Action::WordRight, "word_right", "Next word"),
+    (Action::LineUp, "line_up", "Up a whole line"),
+    (Action::LineDown, "line_down", "Down a whole line"),
+    (Action::LineStart, "line_start", "Start of line"),
+    (Action::LineEnd, "line_end", "End of line"),
+    (
+        Action::Quit,
+        "quit",
+        "Quit (asks first if there are unsaved changes)",
+    ),
+];
+
+/// Bindings before `[keys]` is applied
+const DEFAULT_BINDINGS: &[(&str, Action)] = &[
+    ("f1", Action::Help),
+    ("f2", Action::ToggleFocus),
+    ("f3", Action::ToggleTypewriter),
+    ("ctrl+t", Action::ToggleTypewriter),
+    ("f4", Action::ToggleSound),
+    ("f5", Action::CycleTheme),
+    ("f6", Action::ToggleDoubleSpacing),
+    ("ctrl+r", Action::ToggleHideMarkup),
+    ("ctrl+s", Action::Save),
+    // Ctrl+Shift+S where the terminal reports it, F12 everywhere
+    ("f12", Action::SaveAs),
+    ("ctrl+shift+s", Action::SaveAs),
+    ("ctrl+o", Action::Open),
+    ("ctrl+n", Action::NewBuffer),
+    ("ctrl+w", Action::CloseBuffer),
+    ("f7", Action::PrevBuffer),
+    ("ctrl+pageup", Action::PrevBuffer),
+    ("f8", Action::NextBuffer),
+    ("ctrl+pagedown", Action::NextBuffer),
+    ("f9", Action::Navigator),
+    ("ctrl+e", Action::FileFormat),
+    ("ctrl+p", Action::SavePreferences),
+    ("ctrl+z", Action::Undo),
+    ("ctrl+y", Action::Redo),
+    ("ctrl+x", Action::Cut),
+    ("ctrl+c", Action::Copy),
+    ("ctrl+v", Action::Paste),
+    ("ctrl+f", Action::Find),
+    ("ctrl+h", Action::Replace),
+    ("ctrl+left", Action::WordLeft),
+    ("ctrl+right", Action::WordRight),
+    ("ctrl+up", Action::LineUp),
+    ("ctrl+down", Action::LineDown),
+    ("home", Action::LineStart),
+    ("ctrl+home", Action::LineStart),
+    ("end", Action::LineEnd),
+    ("ctrl+end", Action::LineEnd),
+    ("ctrl+q", Action::Quit),
+];
+
+/// Written as the action in `[keys]` to remove a default binding
+const UNBOUND: &str = "none";
+
+/// Named keys accepted in `[keys]`, with how they are shown
+const KEY_NAMES: &[(&str, KeyCode, &str)] = &[
+    ("left", KeyCode::Left, "Left"),
+    ("right", KeyCode::Right, "Right"),
+    ("up", KeyCode::Up, "Up"),
+    ("down", KeyCode::Down, "Down"),
+    ("home", KeyCode::Home, "Home"),
+    ("end", KeyCode::End, "End"),
+    ("pageup", KeyCode::PageUp, "PgUp"),
+    ("pgup", KeyCode::PageUp, "PgUp"),
+    ("pagedown", KeyCode::PageDown, "PgDn"),
+    ("pgdn", KeyCode::PageDown, "PgDn"),
+    ("tab", KeyCode::Tab, "Tab"),
+    ("enter", KeyCode::Enter, "Enter"),
+    ("esc", KeyCode::Esc, "Esc"),
+    ("backspace", KeyCode::Backspace, "Backspace"),
+    ("delete", KeyCode::Delete, "Del"),
+    ("del", KeyCode::Delete, "Del"),
+    ("insert", KeyCode::Insert, "Ins"),
+    ("space", KeyCode::Char(' '), "Space"),
+];
+
+impl Action {
+    /// Name of the action in config.toml
+    pub fn name(self) -> &'static str {
+        Self::entry(self).1
+    }
+
+    /// What the action does, as the help overlay puts it
+    pub fn description(self) -> &'static str {
+        Self::entry(self).2
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, n, _)| *n == name)
+            .map(|&(action, _, _)| action)
+    }
+
+    /// Cursor motions, which select as they go when Shift is added
+    pub fn is_motion(self) -> bool {
+        matches!(
+            self,
+            Action::WordLeft
+                | Action::WordRight
+                | Action::LineUp
+                | Action::LineDown
+                | Action::LineStart
+                | Action::LineEnd
+        )
+    }
+
+    fn entry(self) -> &'static (Action, &'static str, &'static str) {
+        ACTIONS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .expect("every action is listed")
+    }
+}
+
+/// A key together with the Ctrl, Alt and Shift modifiers held with it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChord {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        // Terminals differ on whether Shift+letter arrives as 'S', or as 's'
+        // with Shift held; settle on the latter
+        match code {
+            KeyCode::Char(c) if c.is_uppercase() => Self {
+                code: KeyCode::Char(c.to_lowercase().next().unwrap_or(c)),
+                modifiers: modifiers | KeyModifiers::SHIFT,
+            },
+            _ => Self { code, modifiers },
+        }
+    }
+
+    /// The chord a key was pressed with
+    pub fn from_event(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+
+    /// The same chord with Shift let go
+    pub fn without_shift(self) -> Self {
+        Self {
+            code: self.code,
+            modifiers: self.modifiers - KeyModifiers::SHIFT,
+        }
+    }
+
+    /// Parse a chord written like `ctrl+shift+s`, `f12` or `alt+pagedown`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim().to_lowercase();
+        let mut parts: Vec<&str> = text.split('+').collect();
+        let key = parts.pop().unwrap_or_default();
+
+        let mut modifiers = KeyModifiers::NONE;
+        for part in parts {
+            modifiers |= match part {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "option" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier \"{part}\"")),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match KEY_NAMES.iter().find(|(name, _, _)| *name == key) {
+                Some(&(_, code, _)) => code,
+                None => match key.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key \"{key}\"")),
+                },
+            },
+        };
+        Ok(Self::new(code, modifiers))
+    }
+
+    /// Whether pressing this would otherwise type a character
+    fn types_text(self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+
+    /// Whether this is a key the editor handles itself, pressed on its
+    /// own: Enter, Backspace, Delete, Esc or an arrow
+    fn is_fixed(self) -> bool {
+        self.modifiers.is_empty()
+            && matches!(
+                self.code,
+                KeyCode::Enter
+                    | KeyCode::Backspace
+                    | KeyCode::Delete
+                    | KeyCode::Esc
+                    | KeyCode::Left
+                    | KeyCode::Right
+                    | KeyCode::Up
+                    | KeyCode::Down
+            )
+    }
+
+    /// Short form for the footer: `^S` rather than `Ctrl+S`
+    pub fn compact(self) -> String {
+        let full = self.to_string();
+        match full.strip_prefix("Ctrl+") {
+            Some(rest) => format!("^{rest}"),
+            None => full,
+        }
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match KEY_NAMES.iter().find(|(_, code, _)| *code == self.code) {
+            Some((_, _, label)) => write!(f, "{label}"),
+            None => match self.code {
+                KeyCode::F(n) => write!(f, "F{n}"),
+                KeyCode::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+                code => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+/// Check one `[keys]` entry: the chord, and the action it is bound to
+/// (`None` when the entry unbinds the chord)
+pub fn parse_binding(chord: &str, action: &str) -> Result<(KeyChord, Option<Action>), String> {
+    let parsed = KeyChord::parse(chord)?;
+    if parsed.types_text() {
+        return Err(format!("\"{chord}\" types text; add ctrl or alt"));
+    }
+    if parsed.is_fixed() {
+        return Err(format!("\"{chord}\" can't be rebound; add ctrl or alt"));
+    }
+    if action == UNBOUND {
+        return Ok((parsed, None));
+    }
+    match Action::from_name(action) {
+        Some(action) => Ok((parsed, Some(action))),
+        None => Err(format!("unknown action \"{action}\"")),
+    }
+}
+
+/// Which action each chord runs
+#[derive(Debug)]
+pub struct Keymap {
+    /// In the order the help overlay lists a chord under its action
+    bindings: Vec<(KeyChord, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|&(chord, action)| {
+                (
+                    KeyChord::parse(chord).expect("default bindings parse"),
+                    action,
+                )
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// The default bindings with the `[keys]` table laid over them. Entries
+    /// that don't parse are skipped; `UserPreferences::validate` reports them.
+    pub fn with_overrides(overrides: &BTreeMap<String, String>) -> Self {
+        let mut keymap = Self::default();
+        for (chord, action) in overrides {
+            let Ok((chord, action)) = parse_binding(chord, action) else {
+                continue;
+            };
+            let existing = keymap.bindings.iter().position(|(c, _)| *c == chord);
+            match (existing, action) {
+                (Some(i), Some(action)) => keymap.bindings[i].1 = action,
+                (Some(i), None) => {
+                    keymap.bindings.remove(i);
+                }
+                (None, Some(action)) => keymap.bindings.push((chord, action)),
+                (None, None) => {}
+            }
+        }
+        keymap
+    }
+
+    /// The action bound to exactly this key press
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.lookup(KeyChord::from_event(key))
+    }
+
+    fn lookup(&self, chord: KeyChord) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(c, _)| *c == chord)
+            .map(|&(_, action)| action)
+    }
+
+    /// The action a key press runs, and whether it should extend the
+    /// selection: a motion pressed with Shift added selects as it moves
+    pub fn resolve(&self, key: &KeyEvent) -> Option<(Action, bool)> {
+        let chord = KeyChord::from_event(key);
+        if let Some(action) = self.lookup(chord) {
+            return Some((action, false));
+        }
+        if !chord.modifiers.contains(KeyModifiers::SHIFT) {
+            return None;
+        }
+        self.lookup(chord.without_shift())
+            .filter(|action| action.is_motion())
+            .map(|action| (action, true))
+    }
+
+    /// Chords bound to `action`
+    pub fn chords(&self, action: Action) -> impl Iterator<Item = KeyChord> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, a)| *a == action)
+            .map(|&(chord, _)| chord)
+    }
+
+    /// Every chord for `action`, for prompts and messages
+    pub fn label(&self, action: Action) -> String {
+        let chords: Vec<String> = self.chords(action).map(|c| c.to_string()).collect();
+        if chords.is_empty() {
+            format!("unbound ({})", action.name())
+        } else {
+            chords.join(", ")
+        }
+    }
+
+    /// `(keys, description)` for each bound action, in help overlay order
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        ACTIONS
+            .iter()
+            .filter(|&&(action, _, _)| self.chords(action).next().is_some())
+            .map(|&(action, _, description)| (self.label(action), description))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_chords_parse_and_display() {
+        for (text, shown) in [
+            ("ctrl+s", "Ctrl+S"),
+            ("Ctrl+Shift+S", "Ctrl+Shift+S"),
+            ("CTRL+S", "Ctrl+S"),
+            ("f12", "F12"),
+            ("alt+pgdn", "Alt+PgDn"),
+            ("control+pagedown", "Ctrl+PgDn"),
+            ("shift+home", "Shift+Home"),
+        ] {
+            assert_eq!(KeyChord::parse(text).unwrap().to_string(), shown, "{text}");
+        }
+        assert!(KeyChord::parse("hyper+s").is_err());
+        assert!(KeyChord::parse("ctrl+").is_err());
+        assert!(KeyChord::parse("f13").is_err());
+        assert_eq!(KeyChord::parse("ctrl+q").unwrap().compact(), "^Q");
+    }
+
+    #[test]
+    fn test_default_bindings() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(&key(KeyCode::F(2), KeyModifiers::NONE)),
+            Some(Action::ToggleFocus)
+        );
+        // However the terminal reports Ctrl+Shift+S
+        for code in [KeyCode::Char('s'), KeyCode::Char('S')] {
+            let pressed = key(code, KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+            assert_eq!(keymap.action(&pressed), Some(Action::SaveAs));
+        }
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('s'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(keymap.label(Action::ToggleTypewriter), "F3, Ctrl+T");
+        assert_eq!(keymap.label(Action::LineStart), "Home, Ctrl+Home");
+        assert_eq!(keymap.help().len(), ACTIONS.len());
+    }
+
+    #[test]
+    fn test_shift_extends_motions_only() {
+        let keymap = Keymap::default();
+        let shift_ctrl_left = key(KeyCode::Left, KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        assert_eq!(
+            keymap.resolve(&shift_ctrl_left),
+            Some((Action::WordLeft, true))
+        );
+        let shift_f2 = key(KeyCode::F(2), KeyModifiers::SHIFT);
+        assert_eq!(keymap.resolve(&shift_f2), None);
+    }
+
+    #[test]
+    fn test_overrides_rebind_and_unbind() {
+        let overrides = BTreeMap::from([
+            ("f2".to_string(), "none".to_string()),
+            ("alt+f".to_string(), "toggle_focus".to_string()),
+            ("f5".to_string(), "undo".to_string()),
+            ("ctrl+k".to_string(), "no_such_action".to_string()),
+        ]);
+        let keymap = Keymap::with_overrides(&overrides);
+        assert_eq!(keymap.action(&key(KeyCode::F(2), KeyModifiers::NONE)), None);
+        assert_eq!(
+            keymap.action(&key(KeyCode::Char('f'), KeyModifiers::ALT)),
+            Some(Action::ToggleFocus)
+        );
+        assert_eq!(keymap.label(Action::Undo), "F5, Ctrl+Z");
+        assert_eq!(keymap.label(Action::CycleTheme), "unbound (cycle_theme)");
+        assert!(keymap
+            .help()
+            .iter()
+            .all(|(_, description)| *description != Action::CycleTheme.description()));
+    }
+
+    #[test]
+    fn test_bindings_that_would_type_are_refused() {
+        assert!(parse_binding("x", "undo").is_err());
+        assert!(parse_binding("shift+x", "undo").is_err());
+        assert!(parse_binding("alt+x", "undo").is_ok());
+        assert_eq!(
+            parse_binding("f1", "none").unwrap(),
+            (KeyChord::parse("f1").unwrap(), None)
+        );
+    }
+
+    #[test]
+    fn test_fixed_keys_are_refused_on_their_own() {
+        for chord in [
+            "enter",
+            "backspace",
+            "delete",
+            "esc",
+            "left",
+            "right",
+            "up",
+            "down",
+        ] {
+            assert!(parse_binding(chord, "undo").is_err(), "{chord}");
+            assert!(parse_binding(chord, "none").is_err(), "{chord}");
+        }
+        assert!(parse_binding("ctrl+enter", "undo").is_ok());
+        assert!(parse_binding("alt+left", "word_left").is_ok());
+
+        let overrides = BTreeMap::from([
+            ("enter".to_string(), "undo".to_string()),
+            ("esc".to_string(), "quit".to_string()),
+        ]);
+        let keymap = Keymap::with_overrides(&overrides);
+        assert_eq!(
+            keymap.resolve(&key(KeyCode::Enter, KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(keymap.resolve(&key(KeyCode::Esc, KeyModifiers::NONE)), None);
+    }
+}
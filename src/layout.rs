@@ -0,0 +1,256 @@
+use clack_core::history::Edit;
+use clack_core::markdown::{self, BlockState, Segment};
+use clack_core::wrap::{self, Row};
+use ropey::Rope;
+
+/// Parsed and wrapped form of one logical line, kept between frames
+pub struct LineLayout {
+    /// Fenced-code state at the start of the line. When an edit above
+    /// changes it, the line is parsed again.
+    state_in: BlockState,
+    state_out: BlockState,
+    pub segments: Vec<Segment>,
+    /// Length of the line in chars, without its newline
+    pub len: usize,
+    /// Visual rows of the line as typed
+    pub rows: Vec<Row>,
+    /// Visual rows with markup hidden; only worked out in hide-markup mode
+    pub concealed_rows: Vec<Row>,
+}
+
+impl LineLayout {
+    /// The rows the line takes up on screen
+    pub fn rows(&self, concealed: bool) -> &[Row] {
+        if concealed {
+            &self.concealed_rows
+        } else {
+            &self.rows
+        }
+    }
+}
+
+/// Layout of the lines of a document, so a frame only parses and wraps the
+/// lines that changed since the last one, and only as far down as it draws.
+/// Entries are cleared by `edited` as the text changes, and all of them when
+/// the wrap width or markup mode does.
+#[derive(Default)]
+pub struct LayoutCache {
+    lines: Vec<Option<LineLayout>>,
+    /// Visual rows of all the lines before each line (one entry more than
+    /// `lines`), counted in the cache's markup mode
+    row_starts: Vec<usize>,
+    /// Lines before this one are laid out with the right fence state, and
+    /// `row_starts` is right up to and including it
+    valid: usize,
+    width: usize,
+    hide_markup: bool,
+}
+
+impl LayoutCache {
+    /// Forget everything, e.g. after the whole text was replaced
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.row_starts.clear();
+        self.valid = 0;
+    }
+
+    /// Note that `edit` has just been made to `content`. Only the lines it
+    /// touched are laid out again; lines further down shift along with it.
+    pub fn edited(&mut self, content: &Rope, edit: &Edit) {
+        let (idx, text) = match edit {
+            Edit::Insert { idx, text } | Edit::Delete { idx, text } => (*idx, text),
+        };
+        let line = content.char_to_line(idx);
+        if line >= self.lines.len() {
+            return;
+        }
+        let breaks = text.matches('\n').count();
+        let (removed, added) = match edit {
+            Edit::Insert { .. } => (0, breaks),
+            Edit::Delete { .. } => (breaks, 0),
+        };
+        let end = (line + removed + 1).min(self.lines.len());
+        self.lines
+            .splice(line..end, std::iter::repeat_with(|| None).take(added + 1));
+        self.row_starts
+            .splice(line + 1..end + 1, std::iter::repeat_n(0, added + 1));
+        self.valid = self.valid.min(line);
+    }
+
+    /// Bring the layout of the lines before `upto` up to date with `content`.
+    /// Lines further down are left until a later call asks for them.
+    pub fn update(&mut self, content: &Rope, width: usize, hide_markup: bool, upto: usize) {
+        let count = content.len_lines();
+        // A line count that doesn't add up means an edit went unreported
+        if width != self.width || hide_markup != self.hide_markup || self.lines.len() != count {
+            self.lines.clear();
+            self.lines.resize_with(count, || None);
+            self.row_starts.clear();
+            self.row_starts.resize(count + 1, 0);
+            self.valid = 0;
+            self.width = width;
+            self.hide_markup = hide_markup;
+        }
+
+        let upto = upto.min(count);
+        let mut state = match self.valid {
+            0 => BlockState::default(),
+            i => self.line(i - 1).state_out,
+        };
+        for i in self.valid..upto {
+            let entry = &mut self.lines[i];
+            if !entry
+                .as_ref()
+                .is_some_and(|layout| layout.state_in == state)
+            {
+                *entry = Some(lay_out(content, i, state, width, hide_markup));
+            }
+            let layout = entry.as_ref().expect("just laid out");
+            state = layout.state_out;
+            self.row_starts[i + 1] = self.row_starts[i] + layout.rows(hide_markup).len();
+        }
+        self.valid = self.valid.max(upto);
+    }
+
+    /// Number of logical lines
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Layout of line `i`, which the last `update` must have reached
+    pub fn line(&self, i: usize) -> &LineLayout {
+        debug_assert!(i < self.valid, "line {i} not laid out");
+        self.lines[i].as_ref().expect("line laid out by update")
+    }
+
+    /// Visual rows taken up by the lines before line `i`, with markup
+    /// hidden if the cache is in that mode. The last `update` must have
+    /// reached the line before `i`.
+    pub fn rows_before(&self, i: usize) -> usize {
+        debug_assert!(i <= self.valid, "line {i} not laid out");
+        self.row_starts[i]
+    }
+}
+
+fn lay_out(
+    content: &Rope,
+    line: usize,
+    state_in: BlockState,
+    width: usize,
+    hide_markup: bool,
+) -> LineLayout {
+    let chars: Vec<char> = content.line(line).chars().filter(|&c| c != '\n').collect();
+    let text: String = chars.iter().collect();
+    let mut state = state_in;
+    let segments = markdown::parse_segments(&text, &mut state);
+    let concealed_rows = if hide_markup {
+        let shown: Vec<char> = markdown::visible_chars(&segments, true)
+            .iter()
+            .map(|&(c, _, _)| c)
+            .collect();
+        wrap::wrap_line(&shown, width)
+    } else {
+        Vec::new()
+    };
+    LineLayout {
+        state_in,
+        state_out: state,
+        segments,
+        len: chars.len(),
+        rows: wrap::wrap_line(&chars, width),
+        concealed_rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(cache: &LayoutCache) -> Vec<String> {
+        (0..cache.len())
+            .map(|i| {
+                cache
+                    .line(i)
+                    .segments
+                    .iter()
+                    .map(|s| s.text.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_edit_only_relays_touched_lines() {
+        let mut content = Rope::from_str("one\ntwo\nthree\n");
+        let mut cache = LayoutCache::default();
+        cache.update(&content, 20, false, usize::MAX);
+
+        // Split "two" in half: one line becomes two
+        let split = Edit::Insert {
+            idx: 5,
+            text: "\n".to_string(),
+        };
+        content.insert(5, "\n");
+        cache.edited(&content, &split);
+        assert_eq!(cache.lines.iter().filter(|l| l.is_none()).count(), 2);
+        cache.update(&content, 20, false, usize::MAX);
+        assert_eq!(texts(&cache), ["one", "t", "wo", "three", ""]);
+
+        // Join them again
+        let join = Edit::Delete {
+            idx: 5,
+            text: "\n".to_string(),
+        };
+        content.remove(5..6);
+        cache.edited(&content, &join);
+        cache.update(&content, 20, false, usize::MAX);
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.rows_before(4), 4);
+    }
+
+    #[test]
+    fn test_opening_a_fence_relays_lines_below() {
+        let mut content = Rope::from_str("intro\n*a*\n");
+        let mut cache = LayoutCache::default();
+        cache.update(&content, 20, false, usize::MAX);
+        assert!(cache.line(1).segments[1].format.italic);
+
+        let fence = Edit::Insert {
+            idx: 0,
+            text: "```\n".to_string(),
+        };
+        content.insert(0, "```\n");
+        cache.edited(&content, &fence);
+        cache.update(&content, 20, false, usize::MAX);
+        assert!(cache.line(2).segments[0].format.code);
+        assert_eq!(cache.line(2).segments[0].text, "*a*");
+    }
+
+    #[test]
+    fn test_update_stops_at_the_lines_asked_for() {
+        // Wraps at 10 columns into two rows per long line
+        let mut content = Rope::from_str("one two three\nfour five six\nshort\n");
+        let mut cache = LayoutCache::default();
+        cache.update(&content, 10, false, 2);
+        assert_eq!(cache.rows_before(2), 4);
+        assert!(cache.lines[2].is_none());
+
+        // An edit in the first line counts rows again from there
+        let shorten = Edit::Delete {
+            idx: 3,
+            text: " two three".to_string(),
+        };
+        content.remove(3..13);
+        cache.edited(&content, &shorten);
+        cache.update(&content, 10, false, 1);
+        assert_eq!(cache.rows_before(1), 1);
+        assert!(cache.lines[1].is_some());
+        cache.update(&content, 10, false, usize::MAX);
+        assert_eq!(cache.rows_before(4), 5);
+        assert_eq!(texts(&cache), ["one", "four five six", "short", ""]);
+    }
+}
@@ -0,0 +1,24 @@
+//! The editor behind the `clack` binary: the terminal front end, sound,
+//! files and settings around the editing engine in `clack-core`. It is a
+//! library as well so the benchmarks can drive it the way the binary does.
+
+pub mod app;
+pub mod buffer;
+pub mod cli;
+pub mod clipboard;
+pub mod config;
+pub mod disk;
+pub mod encoding;
+pub mod input;
+pub mod keymap;
+pub mod layout;
+pub mod minibuffer;
+pub mod project;
+pub mod save;
+pub mod search;
+pub mod sound;
+pub mod swap;
+#[cfg(test)]
+mod test_util;
+pub mod theme;
+pub mod ui;
@@ -1,60 +1,103 @@
-mod app;
-mod config;
-mod markdown;
-mod sound;
-mod theme;
-mod ui;
-
-use app::App;
+use clack_rs::app::App;
+use clack_rs::cli::Cli;
+use clack_rs::config::UserPreferences;
+use clack_rs::{clipboard, input, project, ui};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
-use sound::Sound;
-use std::io;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long to wait for input before running timers (autosave, swap file)
+/// and checking for termination signals
+const TICK: Duration = Duration::from_millis(250);
+
+/// Termination requests delivered as signals while the editor is running
+#[derive(Default)]
+struct Signals {
+    /// SIGTERM: someone asked us to quit, and the terminal is still there to ask the writer
+    terminate: Arc<AtomicBool>,
+    /// SIGHUP: the terminal went away, so nobody can answer a prompt
+    hangup: Arc<AtomicBool>,
+}
+
+impl Signals {
+    #[cfg(unix)]
+    fn register() -> io::Result<Self> {
+        use signal_hook::consts::{SIGHUP, SIGTERM};
+        let signals = Self::default();
+        signal_hook::flag::register(SIGTERM, Arc::clone(&signals.terminate))?;
+        signal_hook::flag::register(SIGHUP, Arc::clone(&signals.hangup))?;
+        Ok(signals)
+    }
+
+    #[cfg(not(unix))]
+    fn register() -> io::Result<Self> {
+        Ok(Self::default())
+    }
+}
 
 fn main() -> anyhow::Result<()> {
-    // 1. Setup Terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    // 1. Read settings: config file first, then command-line overrides
+    let cli = Cli::parse_args();
+    let (mut prefs, config_problems) = match &cli.config {
+        Some(path) => UserPreferences::load_from(path),
+        None => UserPreferences::load(),
+    };
+    cli.apply(&mut prefs);
 
     // 2. Initialize App
-    let args: Vec<String> = std::env::args().collect();
-    let mut app = App::new();
-    if let Some(file_arg) = args.get(1) {
-        let path = PathBuf::from(file_arg);
-        if path.exists() && path.is_file() {
-            match std::fs::read_to_string(&path) {
-                Ok(content) => {
-                    app.content = ropey::Rope::from_str(&content);
-                    app.file_path = Some(path.clone());
-                    app.cursor_idx = 0; // Reset cursor for new file
-                }
-                Err(e) => {
-                    app.set_error(format!("Failed to load {}: {e}", path.display()));
-                }
-            }
-        } else {
-            app.file_path = Some(path.clone());
-            app.content = ropey::Rope::new();
+    let mut app = App::new(prefs);
+    if let Some(path) = &cli.config {
+        app.config_path = Some(path.clone());
+    }
+    if !config_problems.is_empty() {
+        app.set_error(format!("config.toml: {}", config_problems.join("; ")));
+    }
+    if let Some(path) = cli.file {
+        if project::Project::is_project_path(&path) {
+            app.open_project(&path);
+        } else if let Err(e) = app.load_file(path.clone()) {
+            app.set_error(format!("Failed to load {}: {e}", path.display()));
         }
+    } else {
+        app.check_recovery();
+    }
+    if let Some(line) = cli.start_line {
+        app.goto_line(line);
     }
 
-    // 3. Run Event Loop
-    let res = run_app(&mut terminal, &mut app);
+    // 3. Setup Terminal
+    let signals = Signals::register()?;
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
 
-    // 4. Teardown (Restore terminal even if app crashes)
+    // 4. Run Event Loop
+    let res = run_app(&mut terminal, &mut app, &signals);
+
+    // 5. Teardown (Restore terminal even if app crashes)
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
@@ -65,91 +108,56 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn run_app<B: ratatui::backend::Backend>(
+fn run_app<B: ratatui::backend::Backend + Write>(
     terminal: &mut Terminal<B>,
     app: &mut App,
+    signals: &Signals,
 ) -> io::Result<()> {
     loop {
-        terminal.draw(|f| ui::draw(f, app))?;
+        if signals.hangup.swap(false, Ordering::Relaxed) {
+            // No one left to ask: keep the writing for next time and go
+            let _ = app.preserve_unsaved();
+            return Ok(());
+        }
+        if signals.terminate.swap(false, Ordering::Relaxed) {
+            if app.quit_prompt.is_some() {
+                // Asked twice; don't hold up a shutdown any longer
+                let _ = app.preserve_unsaved();
+                return Ok(());
+            }
+            if app.request_quit() {
+                return Ok(());
+            }
+        }
 
-        if let Event::Key(key) = event::read()? {
-            match key.code {
-                KeyCode::Esc => return Ok(()),
-                KeyCode::F(1) => app.toggle_help(),
-                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    app.toggle_mode()
-                }
-                KeyCode::F(3) => app.toggle_mode(),
-                KeyCode::F(2) => app.toggle_focus(),
-                KeyCode::F(4) => app.toggle_sound(),
-                KeyCode::F(5) => app.cycle_theme(),
-                KeyCode::F(6) => app.toggle_double_spacing(),
-                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    if let Err(e) = app.save_to_file() {
-                        app.set_error(format!("Failed to save: {e}"));
-                    }
-                }
-                KeyCode::Enter => {
-                    app.clear_status();
-                    app.enter_key();
-                    // Check if we crossed a page boundary and need to pause for feed sound
-                    if app.check_and_play_page_feed() {
-                        // Brief pause to let the mechanical "feed" action feel real
-                        std::thread::sleep(std::time::Duration::from_millis(
-                            app.config.typewriter.page_feed_pause_ms,
-                        ));
-                    }
-                }
-                KeyCode::Char(c) => {
-                    app.clear_status();
-                    app.insert_char(c);
-                    // Check if we crossed a page boundary while typing
-                    if app.check_and_play_page_feed() {
-                        std::thread::sleep(std::time::Duration::from_millis(
-                            app.config.typewriter.page_feed_pause_ms,
-                        ));
-                    }
-                }
-                KeyCode::Backspace => {
-                    app.clear_status();
-                    app.delete_char();
-                }
-                KeyCode::Delete => {
-                    app.clear_status();
-                    app.delete_char_forward();
-                }
+        app.tick(Instant::now());
+        terminal.draw(|f| ui::draw(f, app))?;
 
-                // Simple Navigation
-                KeyCode::Left => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        app.move_word_left();
-                    } else if app.cursor_idx > 0 {
-                        app.cursor_idx -= 1;
-                    }
-                }
-                KeyCode::Right => {
-                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                        app.move_word_right();
-                    } else if app.cursor_idx < app.content.len_chars() {
-                        app.cursor_idx += 1;
-                    }
-                }
-                KeyCode::Up => app.move_cursor_up(),
-                KeyCode::Down => {
-                    let crossed_page = app.move_cursor_down();
-                    // Play sound and pause if we crossed a page boundary
-                    if crossed_page && app.sound_enabled {
-                        app.audio.trigger(Sound::Feed);
-                        std::thread::sleep(std::time::Duration::from_millis(
-                            app.config.typewriter.page_feed_pause_ms,
-                        ));
-                    }
-                }
-                KeyCode::Home => app.move_to_line_start(),
-                KeyCode::End => app.move_to_line_end(),
+        // Hand freshly cut/copied text to the terminal's clipboard
+        if let Some(text) = app.clipboard_export.take() {
+            let backend = terminal.backend_mut();
+            write!(backend, "{}", clipboard::osc52_sequence(&text))?;
+            Write::flush(backend)?;
+        }
 
-                _ => {}
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) => {
+                input::handle_mouse(app, mouse);
+                continue;
+            }
+            Event::Paste(text) => {
+                app.clear_status();
+                app.paste_text(&text);
+                continue;
             }
+            _ => continue,
+        };
+        if input::handle_key(app, key) {
+            return Ok(());
         }
     }
 }
@@ -0,0 +1,268 @@
+use crate::buffer::CloseScope;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Most files the Open picker will list, so a huge working directory stays responsive
+const MAX_PICKER_FILES: usize = 5000;
+/// Directories the Open picker never descends into
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules"];
+
+/// State of the Save As prompt while it is open
+pub struct SaveAsState {
+    pub input: String,
+    /// Names offered by the last Tab completion
+    pub completions: Vec<String>,
+    /// The typed path exists; waiting for y/n before overwriting it
+    pub confirm_overwrite: bool,
+    /// Close the buffer (or carry on quitting) once the save succeeds,
+    /// when Save was chosen from the unsaved-changes prompt
+    pub then_close: Option<CloseScope>,
+    pub error: Option<String>,
+}
+
+impl SaveAsState {
+    pub fn new(input: String, then_close: Option<CloseScope>) -> Self {
+        Self {
+            input,
+            completions: Vec::new(),
+            confirm_overwrite: false,
+            then_close,
+            error: None,
+        }
+    }
+
+    /// The typed path, with a leading `~/` expanded
+    pub fn path(&self) -> PathBuf {
+        expand_home(self.input.trim())
+    }
+}
+
+/// State of the Open file picker while it is open
+pub struct OpenState {
+    pub query: String,
+    /// Every file under the working directory, relative to it
+    pub files: Vec<String>,
+    /// Indices into `files` matching the query, best first
+    pub matches: Vec<usize>,
+    /// Index into `matches` of the highlighted entry
+    pub selected: usize,
+}
+
+impl OpenState {
+    pub fn new(files: Vec<String>) -> Self {
+        let mut state = Self {
+            query: String::new(),
+            files,
+            matches: Vec::new(),
+            selected: 0,
+        };
+        state.refresh();
+        state
+    }
+
+    /// Re-rank the file list against the query
+    pub fn refresh(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, f)| fuzzy_score(&self.query, f).map(|s| (s, i)))
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.cmp(&a.0)
+                .then_with(|| self.files[a.1].len().cmp(&self.files[b.1].len()))
+                .then_with(|| self.files[a.1].cmp(&self.files[b.1]))
+        });
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+
+    pub fn selected_file(&self) -> Option<&str> {
+        self.matches
+            .get(self.selected)
+            .map(|&i| self.files[i].as_str())
+    }
+
+    /// Move the highlight by `delta` entries, stopping at either end
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.matches.is_empty() {
+            return;
+        }
+        let last = self.matches.len() as isize - 1;
+        self.selected = (self.selected as isize + delta).clamp(0, last) as usize;
+    }
+}
+
+/// A minibuffer prompt for choosing a file
+pub enum FilePrompt {
+    SaveAs(SaveAsState),
+    Open(OpenState),
+}
+
+/// Score how well `query` matches `candidate`: its characters must appear in
+/// order (ignoring case). Runs of consecutive characters and matches at the
+/// start of a path component or word score higher. None if it doesn't match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut previous: Option<usize> = None;
+
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let found =
+            (pos..candidate.len()).find(|&i| candidate[i].to_lowercase().eq(std::iter::once(q)))?;
+        score += 1;
+        if previous == Some(found.wrapping_sub(1)) {
+            score += 5;
+        }
+        if found == 0 || matches!(candidate[found - 1], '/' | '_' | '-' | '.' | ' ') {
+            score += 8;
+        }
+        previous = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+/// Complete the last component of a typed path from the entries on disk.
+/// Returns the new input (extended as far as all candidates agree) and the
+/// candidate names; directories end in `/`.
+pub fn complete_path(input: &str) -> (String, Vec<String>) {
+    let (dir_part, prefix) = match input.rfind('/') {
+        Some(i) => input.split_at(i + 1),
+        None => ("", input),
+    };
+    let dir = if dir_part.is_empty() {
+        PathBuf::from(".")
+    } else {
+        expand_home(dir_part)
+    };
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return (input.to_string(), Vec::new());
+    };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden entries only when asked for with a leading dot
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            Some(if is_dir { format!("{name}/") } else { name })
+        })
+        .collect();
+    candidates.sort();
+
+    let completed = match candidates.as_slice() {
+        [] => prefix.to_string(),
+        [only] => only.clone(),
+        [first, rest @ ..] => rest.iter().fold(first.clone(), |common, c| {
+            common
+                .chars()
+                .zip(c.chars())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect()
+        }),
+    };
+    (format!("{dir_part}{completed}"), candidates)
+}
+
+/// Every file below `root`, as `/`-separated paths relative to it. Hidden
+/// entries and build directories are skipped.
+pub fn list_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+
+    while let Some(rel) = pending.pop() {
+        let Ok(entries) = fs::read_dir(root.join(&rel)) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = rel.join(&name);
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => {
+                    if !SKIPPED_DIRS.contains(&name.as_str()) {
+                        pending.push(path);
+                    }
+                }
+                Ok(_) => {
+                    files.push(path.to_string_lossy().replace('\\', "/"));
+                    if files.len() == MAX_PICKER_FILES {
+                        return files;
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    files
+}
+
+/// Expand a leading `~/` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+
+    #[test]
+    fn test_fuzzy_score_requires_order() {
+        assert!(fuzzy_score("dft", "draft.md").is_some());
+        assert!(fuzzy_score("tfd", "draft.md").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn test_fuzzy_prefers_word_starts_and_runs() {
+        let chapter = fuzzy_score("ch2", "chapters/ch2.md").unwrap();
+        let scattered = fuzzy_score("ch2", "notes/cache_v2.md").unwrap();
+        assert!(chapter > scattered);
+    }
+
+    #[test]
+    fn test_picker_ranks_best_match_first() {
+        let mut state = OpenState::new(vec![
+            "notes/old_draft.md".to_string(),
+            "draft.md".to_string(),
+            "readme.md".to_string(),
+        ]);
+        state.query = "draft".to_string();
+        state.refresh();
+        assert_eq!(state.matches.len(), 2);
+        assert_eq!(state.selected_file(), Some("draft.md"));
+    }
+
+    #[test]
+    fn test_complete_path() {
+        let dir = scratch("clack_test_complete");
+        fs::create_dir_all(dir.join("chapters")).unwrap();
+        fs::write(dir.join("chapter-notes.md"), "").unwrap();
+        fs::write(dir.join("outline.md"), "").unwrap();
+        let base = format!("{}/", dir.display());
+
+        let (input, candidates) = complete_path(&format!("{base}ou"));
+        assert_eq!(input, format!("{base}outline.md"));
+        assert_eq!(candidates, vec!["outline.md"]);
+
+        let (input, candidates) = complete_path(&format!("{base}ch"));
+        assert_eq!(input, format!("{base}chapter"));
+        assert_eq!(candidates, vec!["chapter-notes.md", "chapters/"]);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
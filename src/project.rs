@@ -0,0 +1,230 @@
+use clack_core::page;
+use clack_core::words::count_words;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the manifest that marks a directory as a manuscript project
+pub const MANIFEST_NAME: &str = "clack.toml";
+
+/// Contents of `clack.toml`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    /// Title of the manuscript, written as a heading at the top of the compiled output
+    title: Option<String>,
+    /// Chapter files in reading order, relative to the project directory
+    #[serde(default)]
+    chapters: Vec<PathBuf>,
+    /// Where the compiled manuscript is written, relative to the project directory
+    output: Option<PathBuf>,
+}
+
+/// Word and line totals for one chapter, used for manuscript-wide counts
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ChapterStats {
+    pub words: usize,
+    pub lines: usize,
+}
+
+/// A book-length manuscript split into chapter files
+pub struct Project {
+    /// Directory holding `clack.toml`
+    pub root: PathBuf,
+    pub title: Option<String>,
+    /// Chapter files in order, as paths including `root`
+    pub chapters: Vec<PathBuf>,
+    /// Compiled manuscript path, including `root`
+    pub output: PathBuf,
+    /// Last known counts for each chapter, refreshed from disk or open buffers
+    pub stats: Vec<ChapterStats>,
+}
+
+impl Project {
+    /// Load a project from its manifest, or from the directory containing one
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let manifest_path = if path.is_dir() {
+            path.join(MANIFEST_NAME)
+        } else {
+            path.to_path_buf()
+        };
+        let contents = fs::read_to_string(&manifest_path)
+            .map_err(|e| format!("could not read {}: {e}", manifest_path.display()))?;
+        let manifest: Manifest = toml::from_str(&contents)
+            .map_err(|e| format!("{}: {}", manifest_path.display(), e.message()))?;
+        if manifest.chapters.is_empty() {
+            return Err(format!("{} lists no chapters", manifest_path.display()));
+        }
+
+        let root = manifest_path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        let output = manifest
+            .output
+            .unwrap_or_else(|| PathBuf::from("manuscript.md"));
+        let mut project = Self {
+            title: manifest.title,
+            chapters: manifest.chapters.iter().map(|c| root.join(c)).collect(),
+            output: root.join(output),
+            stats: Vec::new(),
+            root,
+        };
+        project.refresh_stats();
+        Ok(project)
+    }
+
+    /// Whether `path` is a project manifest or a directory holding one
+    pub fn is_project_path(path: &Path) -> bool {
+        if path.is_dir() {
+            path.join(MANIFEST_NAME).is_file()
+        } else {
+            path.file_name().is_some_and(|n| n == MANIFEST_NAME)
+        }
+    }
+
+    /// Re-read every chapter's counts from disk. Missing chapters count as empty.
+    pub fn refresh_stats(&mut self) {
+        self.stats = self
+            .chapters
+            .iter()
+            .map(|path| {
+                fs::read(path)
+                    .map(|bytes| stats_for(&crate::encoding::decode(&bytes).0))
+                    .unwrap_or_default()
+            })
+            .collect();
+    }
+
+    /// Position of `path` in the chapter list
+    pub fn chapter_index(&self, path: &Path) -> Option<usize> {
+        self.chapters
+            .iter()
+            .position(|c| crate::buffer::same_file(c, path))
+    }
+
+    /// Pages taken by the chapters before `index`; each chapter starts on a fresh page
+    pub fn pages_before(&self, index: usize, lines_per_page: usize) -> usize {
+        self.stats[..index]
+            .iter()
+            .map(|s| page::page_count(s.lines, lines_per_page))
+            .sum()
+    }
+
+    /// Chapter name for the navigator: the file name without its directory
+    pub fn chapter_name(&self, index: usize) -> String {
+        crate::buffer::display_name(Some(&self.chapters[index]))
+    }
+
+    /// Join chapter texts into one manuscript, under the title if there is one,
+    /// with a blank line between chapters
+    pub fn compile(&self, texts: &[String]) -> String {
+        let mut out = String::new();
+        if let Some(title) = &self.title {
+            out.push_str(&format!("# {title}\n\n"));
+        }
+        for (i, text) in texts.iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            out.push_str(text.trim_end_matches('\n'));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Word and line counts of a chapter's text
+pub fn stats_for(text: &str) -> ChapterStats {
+    ChapterStats {
+        words: count_words(std::iter::once(text)),
+        lines: text.matches('\n').count() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+
+    #[test]
+    fn test_load_manifest() {
+        let dir = scratch("clack_test_project_load");
+        fs::write(
+            dir.join(MANIFEST_NAME),
+            "title = \"Novel\"\nchapters = [\"one.md\", \"two.md\"]\noutput = \"build/novel.md\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("one.md"), "It was a dark\nand stormy night.\n").unwrap();
+
+        assert!(Project::is_project_path(&dir));
+        let project = Project::load(&dir).unwrap();
+        assert_eq!(
+            project.chapters,
+            vec![dir.join("one.md"), dir.join("two.md")]
+        );
+        assert_eq!(project.output, dir.join("build/novel.md"));
+        assert_eq!(project.stats[0], ChapterStats { words: 7, lines: 3 });
+        // Missing chapters count as empty rather than failing the load
+        assert_eq!(project.stats[1], ChapterStats::default());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_manifest_without_chapters_is_rejected() {
+        let dir = scratch("clack_test_project_empty");
+        fs::write(dir.join(MANIFEST_NAME), "title = \"Nothing yet\"\n").unwrap();
+        assert!(Project::load(&dir).is_err());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_pages_continue_across_chapters() {
+        let project = Project {
+            root: PathBuf::from("."),
+            title: None,
+            chapters: vec![
+                PathBuf::from("a.md"),
+                PathBuf::from("b.md"),
+                PathBuf::from("c.md"),
+            ],
+            output: PathBuf::from("out.md"),
+            stats: vec![
+                ChapterStats {
+                    words: 0,
+                    lines: 10,
+                },
+                ChapterStats {
+                    words: 0,
+                    lines: 11,
+                },
+                ChapterStats { words: 0, lines: 1 },
+            ],
+        };
+        assert_eq!(project.pages_before(0, 10), 0);
+        assert_eq!(project.pages_before(1, 10), 1);
+        assert_eq!(project.pages_before(2, 10), 3);
+    }
+
+    #[test]
+    fn test_compile_joins_chapters() {
+        let project = Project {
+            root: PathBuf::from("."),
+            title: Some("Novel".to_string()),
+            chapters: Vec::new(),
+            output: PathBuf::from("out.md"),
+            stats: Vec::new(),
+        };
+        let texts = vec![
+            "## One\nFirst.\n\n\n".to_string(),
+            "## Two\nSecond.".to_string(),
+        ];
+        assert_eq!(
+            project.compile(&texts),
+            "# Novel\n\n## One\nFirst.\n\n## Two\nSecond.\n"
+        );
+    }
+}
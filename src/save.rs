@@ -0,0 +1,299 @@
+use crate::config::{BackupConfig, BackupMode};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Replace `path` with `chunks` so that a crash or full disk leaves either the
+/// old file or the new one, never a mixture: the text goes to a temporary file
+/// in the same directory, is flushed to disk, takes over the original's
+/// permissions, and is then renamed into place.
+pub fn write_atomic<C: AsRef<[u8]>>(
+    path: &Path,
+    chunks: impl IntoIterator<Item = C>,
+) -> io::Result<()> {
+    // Write through symlinks rather than replacing them
+    let target = match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::canonicalize(path)?,
+        _ => path.to_path_buf(),
+    };
+    let tmp = temp_path(&target);
+
+    let result = write_temp(&tmp, &target, chunks).and_then(|()| {
+        fs::rename(&tmp, &target)
+            .map_err(|e| with_context(e, &format!("could not replace {}", target.display())))
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return result;
+    }
+
+    sync_parent(&target);
+    Ok(())
+}
+
+fn write_temp<C: AsRef<[u8]>>(
+    tmp: &Path,
+    target: &Path,
+    chunks: impl IntoIterator<Item = C>,
+) -> io::Result<()> {
+    let context = |e| with_context(e, &format!("could not write {}", tmp.display()));
+
+    let mut file = fs::File::create(tmp).map_err(context)?;
+    for chunk in chunks {
+        file.write_all(chunk.as_ref()).map_err(context)?;
+    }
+    file.sync_all().map_err(context)?;
+
+    if let Ok(meta) = fs::metadata(target) {
+        fs::set_permissions(tmp, meta.permissions()).map_err(|e| {
+            with_context(
+                e,
+                &format!("could not copy permissions to {}", tmp.display()),
+            )
+        })?;
+    }
+    Ok(())
+}
+
+/// Temporary file next to `target`, hidden and unique to this process
+fn temp_path(target: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(target.file_name().unwrap_or_default());
+    name.push(format!(".clack-tmp-{}", std::process::id()));
+    target.with_file_name(name)
+}
+
+/// Make the rename itself durable. Not every platform lets a directory be
+/// opened and synced, and the file's contents are already safe, so failures
+/// are ignored.
+fn sync_parent(path: &Path) {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = fs::File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
+
+fn with_context(e: io::Error, context: &str) -> io::Error {
+    io::Error::new(e.kind(), format!("{context}: {e}"))
+}
+
+/// Copy the current contents of `path` aside before it is overwritten,
+/// according to `config`. Returns where the copy went, or None if there was
+/// nothing to back up or backups are off.
+pub fn backup(path: &Path, config: &BackupConfig) -> io::Result<Option<PathBuf>> {
+    if config.mode == BackupMode::Off || !path.is_file() {
+        return Ok(None);
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    let dest = match config.mode {
+        BackupMode::Off => return Ok(None),
+        BackupMode::Tilde => path.with_file_name(format!("{name}~")),
+        BackupMode::Timestamped => {
+            let dir = backup_dir(path, config);
+            fs::create_dir_all(&dir)
+                .map_err(|e| with_context(e, &format!("could not create {}", dir.display())))?;
+            dir.join(format!("{name}.{}", timestamp(SystemTime::now())))
+        }
+    };
+
+    fs::copy(path, &dest)
+        .map_err(|e| with_context(e, &format!("could not copy to {}", dest.display())))?;
+
+    if config.mode == BackupMode::Timestamped {
+        prune_backups(&backup_dir(path, config), &name, config.keep)?;
+    }
+    Ok(Some(dest))
+}
+
+/// Directory for timestamped backups: `dir` from the config (with `~/`
+/// expanded), or a hidden `.clack-backups` next to the document
+fn backup_dir(path: &Path, config: &BackupConfig) -> PathBuf {
+    match &config.dir {
+        Some(dir) => match (dir.strip_prefix("~"), std::env::var("HOME")) {
+            (Ok(rest), Ok(home)) => PathBuf::from(home).join(rest),
+            _ => dir.clone(),
+        },
+        None => path.with_file_name(".clack-backups"),
+    }
+}
+
+/// Delete all but the newest `keep` timestamped backups of `name`
+fn prune_backups(dir: &Path, name: &str, keep: usize) -> io::Result<()> {
+    let prefix = format!("{name}.");
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|n| n.strip_prefix(&prefix))
+                .is_some_and(is_timestamp)
+        })
+        .map(|entry| entry.path())
+        .collect();
+    // Timestamps sort chronologically as text
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// UTC time as `YYYYMMDD-HHMMSS`
+fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+fn is_timestamp(s: &str) -> bool {
+    s.len() == 15
+        && s.char_indices()
+            .all(|(i, c)| if i == 8 { c == '-' } else { c.is_ascii_digit() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch;
+    use std::time::Duration;
+
+    #[test]
+    fn test_timestamp_format() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096); // 2024-02-29 12:34:56 UTC
+        assert_eq!(timestamp(time), "20240229-123456");
+        assert!(is_timestamp(&timestamp(time)));
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_and_keeps_permissions() {
+        let dir = scratch("clack_test_atomic");
+        let path = dir.join("draft.md");
+        fs::write(&path, "old text").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+
+        write_atomic(&path, ["new ", "text"]).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new text");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // No temporary file left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_failed_write_leaves_original() {
+        let dir = scratch("clack_test_atomic_fail");
+        let path = dir.join("draft.md");
+        fs::write(&path, "old text").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+        // A directory where the temporary copy would go makes the write fail
+        let blocker = temp_path(&path);
+        fs::create_dir(&blocker).unwrap();
+
+        assert!(write_atomic(&path, ["new text"]).is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"old text");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // Nothing left behind besides the original and the blocker, still empty
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        assert_eq!(fs::read_dir(&blocker).unwrap().count(), 0);
+
+        // Nor when the directory itself is missing
+        let missing = dir.join("no_such_dir").join("draft.md");
+        assert!(write_atomic(&missing, ["text"]).is_err());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_tilde_backup() {
+        let dir = scratch("clack_test_backup_tilde");
+        let path = dir.join("draft.md");
+        fs::write(&path, "first").unwrap();
+        let config = BackupConfig {
+            mode: BackupMode::Tilde,
+            ..BackupConfig::default()
+        };
+
+        let dest = backup(&path, &config).unwrap();
+        assert_eq!(dest, Some(dir.join("draft.md~")));
+        assert_eq!(fs::read_to_string(dir.join("draft.md~")).unwrap(), "first");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_timestamped_backups_rotate() {
+        let dir = scratch("clack_test_backup_rotate");
+        let path = dir.join("draft.md");
+        fs::write(&path, "text").unwrap();
+        let backups = dir.join("backups");
+        fs::create_dir_all(&backups).unwrap();
+        for stamp in ["20240101-000000", "20240102-000000", "20240103-000000"] {
+            fs::write(backups.join(format!("draft.md.{stamp}")), "old").unwrap();
+        }
+        fs::write(backups.join("other.md.20240101-000000"), "other").unwrap();
+        let config = BackupConfig {
+            mode: BackupMode::Timestamped,
+            dir: Some(backups.clone()),
+            keep: 2,
+        };
+
+        backup(&path, &config).unwrap();
+        let mut names: Vec<String> = fs::read_dir(&backups)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0], "draft.md.20240103-000000");
+        assert_eq!(names[2], "other.md.20240101-000000");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+}
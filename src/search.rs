@@ -0,0 +1,142 @@
+use regex::{Regex, RegexBuilder};
+use ropey::Rope;
+
+/// Toggles that change how the query is matched
+#[derive(Clone, Copy, Default)]
+pub struct SearchOptions {
+    /// Treat the query as a regular expression instead of literal text
+    pub regex: bool,
+    /// Only match whole words
+    pub whole_word: bool,
+    /// Match upper/lower case exactly
+    pub case_sensitive: bool,
+}
+
+/// A single option flipped from the prompt
+#[derive(Clone, Copy)]
+pub enum SearchToggle {
+    Regex,
+    WholeWord,
+    CaseSensitive,
+}
+
+impl SearchOptions {
+    pub fn toggle(&mut self, toggle: SearchToggle) {
+        match toggle {
+            SearchToggle::Regex => self.regex = !self.regex,
+            SearchToggle::WholeWord => self.whole_word = !self.whole_word,
+            SearchToggle::CaseSensitive => self.case_sensitive = !self.case_sensitive,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SearchMode {
+    Find,
+    Replace,
+}
+
+/// Which prompt field receives typed characters
+#[derive(Clone, Copy, PartialEq)]
+pub enum SearchField {
+    Query,
+    Replacement,
+}
+
+/// State of the Find / Replace prompt while it is open
+pub struct SearchState {
+    pub mode: SearchMode,
+    pub field: SearchField,
+    pub query: String,
+    pub replacement: String,
+    pub options: SearchOptions,
+    /// Char ranges of every match, in document order
+    pub matches: Vec<(usize, usize)>,
+    /// Index into `matches` of the match the cursor sits on
+    pub current: Option<usize>,
+    /// Cursor position when the prompt opened; incremental search starts here
+    pub origin: usize,
+    /// Message shown when the query is not a valid regular expression
+    pub error: Option<String>,
+}
+
+impl SearchState {
+    pub fn new(mode: SearchMode, origin: usize) -> Self {
+        Self {
+            mode,
+            field: SearchField::Query,
+            query: String::new(),
+            replacement: String::new(),
+            options: SearchOptions::default(),
+            matches: Vec::new(),
+            current: None,
+            origin,
+            error: None,
+        }
+    }
+
+    pub fn current_match(&self) -> Option<(usize, usize)> {
+        self.current.and_then(|i| self.matches.get(i).copied())
+    }
+
+    /// Field currently being edited
+    pub fn active_input(&mut self) -> &mut String {
+        match self.field {
+            SearchField::Query => &mut self.query,
+            SearchField::Replacement => &mut self.replacement,
+        }
+    }
+}
+
+/// A compiled query
+pub struct Matcher {
+    re: Regex,
+    expand_captures: bool,
+}
+
+impl Matcher {
+    pub fn new(query: &str, options: SearchOptions) -> Result<Self, regex::Error> {
+        let mut pattern = if options.regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{pattern})\b");
+        }
+        let re = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .build()?;
+        Ok(Self {
+            re,
+            expand_captures: options.regex,
+        })
+    }
+
+    /// Char ranges of all non-empty matches in the document
+    pub fn find_all(&self, rope: &Rope) -> Vec<(usize, usize)> {
+        let text = rope.to_string();
+        self.re
+            .find_iter(&text)
+            .filter(|m| !m.is_empty())
+            .map(|m| (rope.byte_to_char(m.start()), rope.byte_to_char(m.end())))
+            .collect()
+    }
+
+    /// Text to substitute for the match starting at byte `byte_start` of
+    /// `text`, the whole document. In regex mode `$1`/`${name}` in the
+    /// template refer to capture groups.
+    pub fn replacement_for(&self, text: &str, byte_start: usize, template: &str) -> String {
+        if !self.expand_captures {
+            return template.to_string();
+        }
+        match self.re.captures_at(text, byte_start) {
+            Some(caps) if caps.get(0).is_some_and(|m| m.start() == byte_start) => {
+                let mut out = String::new();
+                caps.expand(template, &mut out);
+                out
+            }
+            _ => template.to_string(),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Recovery copy of `file`: a hidden `.<name>.clack-swap` in the same directory
+pub fn swap_path(file: &Path) -> PathBuf {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(file.file_name().unwrap_or_default());
+    name.push(".clack-swap");
+    file.with_file_name(name)
+}
+
+/// Name of the file whose swap file is called `swap_name`, if it is one
+pub fn swapped_name(swap_name: &str) -> Option<&str> {
+    swap_name.strip_prefix('.')?.strip_suffix(".clack-swap")
+}
+
+/// Delete the swap file, ignoring one that isn't there
+pub fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swap_path_is_hidden_sibling() {
+        assert_eq!(
+            swap_path(Path::new("notes/draft.md")),
+            PathBuf::from("notes/.draft.md.clack-swap")
+        );
+        assert_eq!(
+            swap_path(Path::new("Untitled.md")),
+            PathBuf::from(".Untitled.md.clack-swap")
+        );
+        assert_eq!(swapped_name(".draft.md.clack-swap"), Some("draft.md"));
+        assert_eq!(swapped_name("draft.md"), None);
+    }
+}
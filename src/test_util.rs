@@ -0,0 +1,26 @@
+//! Helpers shared by the unit tests.
+
+use crate::app::App;
+use crate::config::UserPreferences;
+use std::fs;
+use std::path::PathBuf;
+
+/// An app with the default settings and sound off, that saves preferences
+/// nowhere rather than over the real config file
+pub fn app() -> App {
+    let mut app = App::new(UserPreferences {
+        sound_enabled: false,
+        ..UserPreferences::default()
+    });
+    app.config_path = None;
+    app
+}
+
+/// An empty directory `name` under the system temp dir, cleared of
+/// anything an earlier run left there
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -9,11 +9,13 @@ pub struct Theme {
     pub border: Color,
     pub header_bg: Color,
     pub header_fg: Color,
-    pub accent: Color,      // For "Clack" text, active toggles
-    pub dim_text: Color,    // For Focus Mode inactive lines
-    pub guide_color: Color, // For margin guides and page breaks
-    pub status_ok: Color,   // Green usually
-    pub status_bad: Color,  // Red usually
+    pub accent: Color,       // For "Clack" text, active toggles
+    pub dim_text: Color,     // For Focus Mode inactive lines
+    pub guide_color: Color,  // For margin guides and page breaks
+    pub status_ok: Color,    // Green usually
+    pub status_bad: Color,   // Red usually
+    pub selection: Color,    // Background for selected text
+    pub search_match: Color, // Background for search matches
 }
 
 impl Theme {
@@ -31,6 +33,8 @@ impl Theme {
             guide_color: Color::DarkGray,
             status_ok: Color::Green,
             status_bad: Color::Red,
+            selection: Color::Rgb(60, 60, 90),
+            search_match: Color::Rgb(70, 70, 40),
         }
     }
 
@@ -44,11 +48,13 @@ impl Theme {
             border: Color::Rgb(180, 170, 150),
             header_bg: Color::Rgb(238, 232, 213),
             header_fg: Color::Black,
-            accent: Color::Rgb(38, 139, 210),       // Cyan/Blueish
-            dim_text: Color::Rgb(200, 200, 190),    // Dim version of black on cream
-            guide_color: Color::Rgb(220, 215, 190), // Subtle light gray for paper
-            status_ok: Color::Rgb(133, 153, 0),     // Olive Green
-            status_bad: Color::Rgb(220, 50, 47),    // Red
+            accent: Color::Rgb(38, 139, 210),        // Cyan/Blueish
+            dim_text: Color::Rgb(200, 200, 190),     // Dim version of black on cream
+            guide_color: Color::Rgb(220, 215, 190),  // Subtle light gray for paper
+            status_ok: Color::Rgb(133, 153, 0),      // Olive Green
+            status_bad: Color::Rgb(220, 50, 47),     // Red
+            selection: Color::Rgb(238, 220, 160),    // Highlighter yellow
+            search_match: Color::Rgb(215, 230, 240), // Pale blue
         }
     }
 
@@ -69,6 +75,8 @@ impl Theme {
             guide_color: Color::Rgb(80, 55, 0), // Dimmer, less saturated amber
             status_ok: amber,
             status_bad: Color::Red,
+            selection: Color::Rgb(70, 50, 0),
+            search_match: Color::Rgb(45, 32, 0),
         }
     }
 }
@@ -80,6 +88,15 @@ pub enum ThemeType {
 }
 
 impl ThemeType {
+    /// Name used for the theme in config.toml
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThemeType::Dark => "Dark",
+            ThemeType::Light => "Paper",
+            ThemeType::Retro => "Retro",
+        }
+    }
+
     pub fn next(&self) -> Self {
         match self {
             ThemeType::Dark => ThemeType::Light,
@@ -1,17 +1,91 @@
 use crate::app::App;
-use crate::markdown;
+use crate::buffer::{self, CloseScope};
+use crate::disk::DiffLine;
+use crate::encoding::{Encoding, LineEnding};
+use crate::keymap::{Action, Keymap};
+use crate::layout::LineLayout;
+use crate::minibuffer::{FilePrompt, OpenState, SaveAsState};
+use crate::project::Project;
+use crate::search::{SearchField, SearchMode, SearchState};
+use crate::theme::Theme;
+use clack_core::markdown::{self, Format};
+use clack_core::page;
+use clack_core::wrap;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Padding, Paragraph},
+    widgets::{
+        block::{Position, Title},
+        Block, Borders, Clear, Padding, Paragraph,
+    },
     Frame,
 };
+use std::path::Path;
+
+/// Where the text landed on screen in the last frame, so mouse positions can be
+/// mapped back to character indices
+#[derive(Default)]
+pub struct ViewMap {
+    /// Screen position of the first text column/row
+    pub origin_x: u16,
+    pub origin_y: u16,
+    /// Number of text rows visible inside the paper
+    pub height: u16,
+    /// Where each visual row on the paper, from the top, gets its text;
+    /// `None` for decoration rows such as page separators and double-spacing
+    /// gaps
+    pub rows: Vec<Option<TextRow>>,
+}
+
+/// Source of one visual row of text
+#[derive(Clone, Debug, PartialEq)]
+pub enum TextRow {
+    /// Columns run straight through the text: the first char index and the
+    /// furthest cursor column
+    Straight { start: usize, max_col: usize },
+    /// Markup is hidden on this row, so each column has its own char index.
+    /// The last entry is where a click past the end of the row lands.
+    Mapped(Vec<usize>),
+}
+
+impl TextRow {
+    /// Character index shown at `col`, clamped to the row
+    pub fn char_at(&self, col: usize) -> usize {
+        match self {
+            Self::Straight { start, max_col } => start + col.min(*max_col),
+            Self::Mapped(columns) => columns[col.min(columns.len() - 1)],
+        }
+    }
+
+    /// Character index a click past the end of the row lands on
+    fn end(&self) -> usize {
+        self.char_at(usize::MAX)
+    }
+}
+
+impl ViewMap {
+    /// Character index under a screen position, if it falls on a text row
+    pub fn char_at(&self, column: u16, row: u16) -> Option<usize> {
+        if row < self.origin_y || row >= self.origin_y + self.height {
+            return None;
+        }
+        let col = column.saturating_sub(self.origin_x) as usize;
+
+        match self.rows.get((row - self.origin_y) as usize) {
+            Some(Some(text_row)) => Some(text_row.char_at(col)),
+            Some(None) => None,
+            // Clicking below the end of the text puts the cursor at the very end
+            None => self.rows.iter().rev().flatten().next().map(TextRow::end),
+        }
+    }
+}
 
 pub fn draw(f: &mut Frame, app: &mut App) {
     // Get cached counts before borrowing theme (to avoid borrow checker issues)
     let word_count = app.get_word_count();
     let char_count = app.get_char_count();
+    let manuscript_words = app.manuscript_word_count();
 
     let theme = &app.theme;
 
@@ -45,7 +119,7 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
     let unsaved_indicator = if app.has_unsaved_changes { " *" } else { "" };
 
-    let header_text = Line::from(vec![
+    let mut header_spans = vec![
         Span::styled(
             " Clack ",
             Style::default()
@@ -54,7 +128,24 @@ pub fn draw(f: &mut Frame, app: &mut App) {
                 .add_modifier(Modifier::BOLD),
         ),
         Span::raw(format!(" {filepath}{unsaved_indicator}")),
-    ]);
+    ];
+    // With several documents open, list them all; the active one stands out
+    let buffers = app.buffer_list();
+    if buffers.len() > 1 {
+        header_spans.push(Span::styled("  │", Style::default().fg(theme.guide_color)));
+        for (idx, (name, modified)) in buffers.iter().enumerate() {
+            let marker = if *modified { "*" } else { "" };
+            let style = if idx == app.active_buffer() {
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.header_fg)
+            };
+            header_spans.push(Span::styled(format!(" {}:{name}{marker}", idx + 1), style));
+        }
+    }
+    let header_text = Line::from(header_spans);
     f.render_widget(
         Paragraph::new(header_text).style(Style::default().bg(theme.header_bg).fg(theme.header_fg)),
         header_area,
@@ -93,14 +184,81 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     // Use bell_column for visual wrapping to match typewriter margin behavior
     let wrap_width = app.config.typewriter.bell_column;
 
-    // --- MANUAL WRAPPING & CURSOR MAPPING ---
+    // --- WORD WRAPPING & CURSOR MAPPING ---
     let (cursor_col, cursor_row) = app.get_cursor_position();
+    let selection = app.selection_range();
+    let (search_matches, current_match) = match &app.search {
+        Some(search) => (search.matches.as_slice(), search.current_match()),
+        None => (&[][..], None),
+    };
+    let lines_per_page = app.config.typewriter.lines_per_page;
+    let hide_markup = app.hide_markup;
+    let inner_height = term_height.saturating_sub(
+        2 + app.config.layout.pad_top as usize + app.config.layout.pad_bottom as usize,
+    );
+    // Every line takes at least one row, so nothing below this can show
+    app.layout.update(
+        &app.doc.content,
+        wrap_width,
+        hide_markup,
+        cursor_row + inner_height + 1,
+    );
+    let layout = &app.layout;
+
+    // Markup stays visible on the line being edited, so the cursor's column
+    // there is always its column in the text
+    let conceal = |i: usize| hide_markup && i != cursor_row;
+    // Visual rows for a line, with the double-spacing gap and any page
+    // break after it
+    let height_of = |i: usize, line: &LineLayout| {
+        let page_break = if page::ends_page(i, lines_per_page) {
+            3
+        } else {
+            0
+        };
+        line.rows(conceal(i)).len() + usize::from(app.double_spacing) + page_break
+    };
+
+    let (cursor_row_idx, visual_cursor_x) = wrap::locate(&layout.line(cursor_row).rows, cursor_col);
+    // Lines above the cursor's have their markup concealed, as the cache counts them
+    let cursor_line_top = layout.rows_before(cursor_row)
+        + cursor_row * usize::from(app.double_spacing)
+        + cursor_row / lines_per_page * 3;
+    let visual_cursor_y = cursor_line_top + cursor_row_idx;
+
+    // --- SCROLLING LOGIC ---
+    let center_line = inner_height / 2;
+
+    let scroll_offset = if app.typewriter_mode {
+        visual_cursor_y.saturating_sub(center_line)
+    } else {
+        visual_cursor_y.saturating_sub(inner_height.saturating_sub(1))
+    };
+
+    // Only the lines that show on the paper are styled
     let mut visual_lines: Vec<Line> = Vec::new();
-    let mut visual_cursor_y = 0;
-    let mut visual_cursor_x = 0;
+    let mut row_map: Vec<Option<TextRow>> = Vec::new();
+
+    // Step up from the cursor's line to the first line on the paper
+    let mut first = cursor_row;
+    let mut top = cursor_line_top;
+    while first > 0 && top > scroll_offset {
+        first -= 1;
+        top -= height_of(first, layout.line(first));
+    }
+    let first_top = top;
+
+    for i in first..layout.len() {
+        if top >= scroll_offset + inner_height {
+            break;
+        }
+        let line = layout.line(i);
+        top += height_of(i, line);
 
-    for (i, line) in app.content.lines().enumerate() {
-        let parsed_line = markdown::parse_line(&line.to_string(), theme);
+        let line_start = app.doc.content.line_to_char(i);
+        let conceal = conceal(i);
+        let shown = markdown::visible_chars(&line.segments, conceal);
+        let rows = line.rows(conceal);
 
         // --- FOCUS MODE LOGIC ---
         let distance = i.abs_diff(cursor_row);
@@ -114,54 +272,67 @@ pub fn draw(f: &mut Frame, app: &mut App) {
             None
         };
 
-        let raw_chars: Vec<(char, Style)> = parsed_line
-            .spans
+        let raw_chars: Vec<(char, Style)> = shown
             .iter()
-            .flat_map(|s| {
-                let mut base_style = s.style;
-                if base_style.fg.is_none() || base_style.fg == Some(Color::Reset) {
-                    base_style = base_style.fg(theme.base_fg);
-                }
-
-                let final_style = if let Some(ov) = style_override {
+            .map(|&(c, format, offset)| {
+                let base_style = style_for(format, theme);
+                let style = if let Some(ov) = style_override {
                     base_style.fg(ov.fg.unwrap()).add_modifier(ov.add_modifier)
                 } else {
                     base_style
                 };
-                s.content.chars().map(move |c| (c, final_style))
+                let idx = line_start + offset;
+                let in_range = |range: Option<(usize, usize)>| {
+                    range.is_some_and(|(start, end)| (start..end).contains(&idx))
+                };
+                if in_range(selection) || in_range(current_match) {
+                    (c, style.bg(theme.selection))
+                } else if in_ranges(search_matches, idx) {
+                    (c, style.bg(theme.search_match))
+                } else {
+                    (c, style)
+                }
             })
             .collect();
 
-        let mut current_spans = Vec::new();
-        let mut width_counter = 0;
-        let start_index = visual_lines.len();
-
-        if raw_chars.is_empty() {
-            visual_lines.push(Line::from(vec![]));
-        } else {
-            for (c, style) in raw_chars {
-                if width_counter >= wrap_width {
-                    visual_lines.push(Line::from(current_spans));
-                    current_spans = Vec::new();
-                    width_counter = 0;
-                }
-                current_spans.push(Span::styled(c.to_string(), style));
-                width_counter += 1;
-            }
-            if !current_spans.is_empty() {
-                visual_lines.push(Line::from(current_spans));
+        for (r, row) in rows.iter().enumerate() {
+            let (from, to) = (row.start, row.end);
+            let mut spans: Vec<Span> = raw_chars[from..to]
+                .iter()
+                .map(|(c, style)| Span::styled(c.to_string(), *style))
+                .collect();
+            if row.hyphen {
+                spans.push(Span::styled("-", Style::default().fg(theme.guide_color)));
             }
+            visual_lines.push(Line::from(spans));
+            let is_last = r + 1 == rows.len();
+            row_map.push(Some(if conceal {
+                let mut columns: Vec<usize> = shown[from..to]
+                    .iter()
+                    .map(|&(_, _, offset)| line_start + offset)
+                    .collect();
+                if is_last {
+                    columns.push(line_start + line.len);
+                }
+                TextRow::Mapped(columns)
+            } else {
+                TextRow::Straight {
+                    start: line_start + row.start,
+                    max_col: row.max_col(is_last),
+                }
+            }));
         }
 
         // Add blank line for double spacing if enabled
         if app.double_spacing {
             visual_lines.push(Line::from(vec![]));
+            row_map.push(None);
         }
 
         // Insert page break AFTER the last line of each page
         // Check if the CURRENT line (i) is the last line of a page
-        if (i + 1) % app.config.typewriter.lines_per_page == 0 {
-            let next_page_number = ((i + 1) / app.config.typewriter.lines_per_page) + 1;
+        if page::ends_page(i, lines_per_page) {
+            let next_page_number = page::page_of(i + 1, lines_per_page);
             let label = format!(" Page {next_page_number} ");
             let label_len = label.len();
 
@@ -183,42 +354,14 @@ pub fn draw(f: &mut Frame, app: &mut App) {
                 Style::default().fg(theme.guide_color),
             )]));
             visual_lines.push(Line::from(vec![])); // Blank line after
-        }
-
-        if i == cursor_row {
-            let row_offset = cursor_col / wrap_width;
-            let col_offset = cursor_col % wrap_width;
-            let target_visual_row_idx = start_index + row_offset;
-
-            if target_visual_row_idx >= visual_lines.len() {
-                visual_lines.push(Line::from(vec![]));
-            }
-
-            visual_cursor_y = target_visual_row_idx;
-            visual_cursor_x = col_offset;
+            row_map.extend([None, None, None]);
         }
     }
 
-    // --- SCROLLING LOGIC ---
-    let inner_height = term_height.saturating_sub(
-        2 + app.config.layout.pad_top as usize + app.config.layout.pad_bottom as usize,
-    );
-    let center_line = inner_height / 2;
-
-    let scroll_offset = if app.typewriter_mode {
-        if visual_cursor_y > center_line {
-            (visual_cursor_y - center_line) as u16
-        } else {
-            0
-        }
-    } else {
-        let max_visible_row = inner_height.saturating_sub(1);
-        if visual_cursor_y > max_visible_row {
-            (visual_cursor_y - max_visible_row) as u16
-        } else {
-            0
-        }
-    };
+    // The first line shown may start above the top of the paper
+    let above = scroll_offset - first_top;
+    visual_lines.drain(..above.min(visual_lines.len()));
+    row_map.drain(..above.min(row_map.len()));
 
     let border_type = if app.config.layout.fancy_borders {
         ratatui::widgets::BorderType::Double
@@ -244,20 +387,20 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     let cursor_visual_y_start = text_area.y + 1 + app.config.layout.pad_top; // +1 for block's top border
     let cursor_visual_x_start = text_area.x + 1 + app.config.layout.pad_left;
 
-    // Get character at cursor position BEFORE moving visual_lines
-    let char_at_cursor = if visual_cursor_y < visual_lines.len() {
-        visual_lines[visual_cursor_y]
-            .spans
-            .get(visual_cursor_x)
-            .and_then(|span| span.content.chars().next())
-            .unwrap_or(' ')
-    } else {
-        ' '
+    app.view = ViewMap {
+        origin_x: cursor_visual_x_start,
+        origin_y: cursor_visual_y_start,
+        height: inner_height as u16,
+        rows: row_map,
     };
 
-    let paragraph = Paragraph::new(visual_lines)
-        .style(Style::default().fg(theme.base_fg).bg(theme.paper_bg))
-        .scroll((scroll_offset, 0));
+    let char_at_cursor = match app.doc.content.get_char(app.doc.cursor) {
+        Some(c) if c != '\n' => c,
+        _ => ' ',
+    };
+
+    let paragraph =
+        Paragraph::new(visual_lines).style(Style::default().fg(theme.base_fg).bg(theme.paper_bg));
 
     f.render_widget(paper_block, text_area);
     f.render_widget(paragraph, inner_text_area);
@@ -312,7 +455,9 @@ pub fn draw(f: &mut Frame, app: &mut App) {
     }
 
     // --- FOOTER ---
-    let status_text = if let Some(ref msg) = app.status_message {
+    let status_text = if let Some(ref search) = app.search {
+        search_prompt_line(search, theme)
+    } else if let Some(ref msg) = app.status_message {
         // Show status message if present
         Line::from(vec![Span::styled(
             format!(" {msg}"),
@@ -379,12 +524,20 @@ pub fn draw(f: &mut Frame, app: &mut App) {
             ),
             Span::raw(" | "),
             Span::styled(
-                format!("{word_count} w / {char_count} c"),
+                match manuscript_words {
+                    Some(total) => format!("{word_count} w / {total} w total"),
+                    None => format!("{word_count} w / {char_count} c"),
+                },
                 Style::default().fg(theme.header_fg),
             ),
             Span::raw(" | "),
             Span::styled(
-                "F1:Help ^S:Save Esc:Quit",
+                app.text_format.to_string(),
+                Style::default().fg(theme.header_fg),
+            ),
+            Span::raw(" | "),
+            Span::styled(
+                footer_shortcuts(&app.config.keymap),
                 Style::default().fg(theme.header_fg),
             ),
         ])
@@ -397,89 +550,520 @@ pub fn draw(f: &mut Frame, app: &mut App) {
 
     // --- HELP OVERLAY ---
     if app.show_help {
-        draw_help_overlay(f, theme);
+        draw_help_overlay(f, theme, &app.config.keymap, &mut app.help_scroll);
+    }
+
+    // --- CHAPTER NAVIGATOR ---
+    if let (Some(selected), Some(project)) = (app.navigator, &app.project) {
+        let lines_per_page = app.config.typewriter.lines_per_page;
+        draw_navigator(
+            f,
+            theme,
+            project,
+            selected,
+            app.current_chapter(),
+            lines_per_page,
+        );
+    }
+
+    // --- SAVE AS / OPEN ---
+    match &app.file_prompt {
+        Some(FilePrompt::SaveAs(state)) => draw_save_as(f, theme, state),
+        Some(FilePrompt::Open(state)) => draw_file_picker(f, theme, state),
+        None => {}
+    }
+
+    // --- QUIT CONFIRMATION ---
+    if let Some(scope) = app.quit_prompt {
+        draw_quit_prompt(f, app, scope);
+    }
+
+    // --- FILE FORMAT ---
+    if app.format_prompt {
+        draw_format_prompt(f, app);
+    }
+
+    // --- CHANGED ON DISK ---
+    if let Some(change) = &app.external_change {
+        match &change.diff {
+            Some(diff) => draw_external_diff(f, app, diff, change.scroll),
+            None => draw_external_prompt(f, app),
+        }
+    }
+
+    // --- CRASH RECOVERY ---
+    if let Some(swap) = &app.recovery_prompt {
+        draw_recovery_prompt(f, theme, swap, &app.config.keymap);
     }
 }
 
-fn draw_help_overlay(f: &mut Frame, theme: &crate::theme::Theme) {
-    let size = f.size();
+/// Display style for a segment
+fn style_for(format: Format, theme: &Theme) -> Style {
+    if format.markup || format.rule {
+        return Style::default().fg(theme.guide_color);
+    }
+    if format.bullet {
+        return Style::default()
+            .fg(theme.accent)
+            .add_modifier(Modifier::BOLD);
+    }
 
-    // Create centered modal dimensions
-    let modal_width = 60;
-    let modal_height = 18;
-    let modal_x = (size.width.saturating_sub(modal_width)) / 2;
-    let modal_y = (size.height.saturating_sub(modal_height)) / 2;
+    let mut style = Style::default().fg(if format.done {
+        theme.dim_text
+    } else {
+        theme.base_fg
+    });
+    if format.code {
+        style = style.fg(theme.accent);
+    }
+    if format.link {
+        style = style.fg(theme.accent).add_modifier(Modifier::UNDERLINED);
+    }
+    if format.bold || format.heading > 0 {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if format.heading == 1 {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    if format.italic || format.quote {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if format.strike {
+        style = style.add_modifier(Modifier::CROSSED_OUT);
+    }
+    style
+}
 
-    let modal_area = Rect {
-        x: modal_x,
-        y: modal_y,
-        width: modal_width,
-        height: modal_height,
+/// Whether `idx` falls inside one of the sorted, non-overlapping `ranges`
+fn in_ranges(ranges: &[(usize, usize)], idx: usize) -> bool {
+    let i = ranges.partition_point(|&(_, end)| end <= idx);
+    ranges.get(i).is_some_and(|&(start, _)| start <= idx)
+}
+
+/// Footer prompt for the Find / Replace minibuffer
+fn search_prompt_line(search: &SearchState, theme: &Theme) -> Line<'static> {
+    let label_style = Style::default().fg(theme.header_fg);
+    let field_style = |field: SearchField| {
+        if search.field == field {
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(theme.header_fg)
+        }
+    };
+    let flag_style = |on: bool| {
+        if on {
+            Style::default().fg(theme.status_ok)
+        } else {
+            Style::default().fg(theme.guide_color)
+        }
+    };
+
+    let mut spans = vec![
+        Span::styled(" Find: ", label_style),
+        Span::styled(
+            format!("{}▏", search.query),
+            field_style(SearchField::Query),
+        ),
+    ];
+    if search.mode == SearchMode::Replace {
+        spans.push(Span::styled("  Replace: ", label_style));
+        spans.push(Span::styled(
+            format!("{}▏", search.replacement),
+            field_style(SearchField::Replacement),
+        ));
+    }
+
+    let (result, result_style) = if let Some(ref error) = search.error {
+        // regex errors span several lines; the last one holds the reason
+        let summary = error.lines().last().unwrap_or("invalid pattern").trim();
+        (
+            format!("  {summary}"),
+            Style::default().fg(theme.status_bad),
+        )
+    } else if search.query.is_empty() {
+        (String::new(), label_style)
+    } else if search.matches.is_empty() {
+        (
+            "  no matches".to_string(),
+            Style::default().fg(theme.status_bad),
+        )
+    } else {
+        let current = search.current.map_or(0, |i| i + 1);
+        (
+            format!("  [{current}/{}]", search.matches.len()),
+            Style::default().fg(theme.status_ok),
+        )
     };
+    spans.push(Span::styled(result, result_style));
+
+    spans.push(Span::raw("  "));
+    spans.push(Span::styled(
+        "Aa",
+        flag_style(search.options.case_sensitive),
+    ));
+    spans.push(Span::raw(" "));
+    spans.push(Span::styled("W", flag_style(search.options.whole_word)));
+    spans.push(Span::raw(" "));
+    spans.push(Span::styled(".*", flag_style(search.options.regex)));
+
+    let hints = match search.mode {
+        SearchMode::Find => "  Enter/↓:Next ↑:Prev Esc:Close",
+        SearchMode::Replace => "  Tab:Field Enter:Replace Alt+A:All Esc:Close",
+    };
+    spans.push(Span::styled(hints, label_style));
+    Line::from(spans)
+}
+
+/// "F1:Help ^S:Save ^Q:Quit", or whatever those actions are bound to
+fn footer_shortcuts(keymap: &Keymap) -> String {
+    [
+        (Action::Help, "Help"),
+        (Action::Save, "Save"),
+        (Action::Quit, "Quit"),
+    ]
+    .iter()
+    .filter_map(|&(action, name)| {
+        let chord = keymap.chords(action).next()?;
+        Some(format!("{}:{name}", chord.compact()))
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// " (undo with Ctrl+Z)" after a prompt choice that replaces the text, if undo has a key
+fn undo_hint(keymap: &Keymap) -> String {
+    keymap
+        .chords(Action::Undo)
+        .next()
+        .map(|chord| format!(" (undo with {chord})"))
+        .unwrap_or_default()
+}
+
+/// Shortcut list generated from the keymap, scrolled with Up/Down when it
+/// doesn't fit on screen
+fn draw_help_overlay(f: &mut Frame, theme: &Theme, keymap: &Keymap, scroll: &mut usize) {
+    let size = f.size();
+    let entries = keymap.help();
+    let keys_width = entries
+        .iter()
+        .map(|(keys, _)| keys.chars().count())
+        .max()
+        .unwrap_or(0);
 
     // Create help content
-    let help_text = vec![
-        Line::from(vec![Span::styled(
-            "                    CLACK - HELP                    ",
-            Style::default()
-                .fg(theme.base_bg)
-                .bg(theme.accent)
-                .add_modifier(Modifier::BOLD),
-        )]),
+    let mut help_text = vec![
         Line::from(""),
         Line::from(vec![Span::styled(
             "  Keyboard shortcuts",
             Style::default().add_modifier(Modifier::BOLD),
         )]),
         Line::from(""),
+    ];
+    for (keys, description) in &entries {
+        help_text.push(Line::from(vec![
+            Span::styled(
+                format!("  {keys:<keys_width$}"),
+                Style::default().fg(theme.accent),
+            ),
+            Span::raw(format!("  {description}")),
+        ]));
+    }
+    let widest = help_text.iter().map(Line::width).max().unwrap_or(0) as u16;
+
+    // Centered modal: a title row, the list, and a border all round
+    let modal_width = (widest + 4).max(60).min(size.width);
+    let modal_height = (help_text.len() as u16 + 3).min(size.height);
+    let modal_area = Rect {
+        x: (size.width - modal_width) / 2,
+        y: (size.height - modal_height) / 2,
+        width: modal_width,
+        height: modal_height,
+    };
+
+    // Rows for the list below the title; the scroll stops at the last entry
+    let visible = modal_height.saturating_sub(3) as usize;
+    let max_scroll = help_text.len().saturating_sub(visible);
+    *scroll = (*scroll).min(max_scroll);
+
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border))
+        .style(Style::default().bg(theme.paper_bg).fg(theme.base_fg));
+    if max_scroll > 0 {
+        block = block.title(
+            Title::from(" \u{2191}/\u{2193} scroll ")
+                .position(Position::Bottom)
+                .alignment(Alignment::Right),
+        );
+    }
+
+    f.render_widget(Clear, modal_area);
+    f.render_widget(block, modal_area);
+
+    // Render text inside the modal
+    let inner_area = Rect {
+        x: modal_area.x + 1,
+        y: modal_area.y + 1,
+        width: modal_area.width.saturating_sub(2),
+        height: modal_area.height.saturating_sub(2),
+    };
+    let title_area = Rect {
+        height: 1.min(inner_area.height),
+        ..inner_area
+    };
+    let list_area = Rect {
+        y: inner_area.y + title_area.height,
+        height: inner_area.height - title_area.height,
+        ..inner_area
+    };
+
+    let title = Paragraph::new(Line::from(Span::styled(
+        "CLACK - HELP",
+        Style::default()
+            .fg(theme.base_bg)
+            .bg(theme.accent)
+            .add_modifier(Modifier::BOLD),
+    )))
+    .alignment(Alignment::Center)
+    .style(Style::default().bg(theme.accent));
+    f.render_widget(title, title_area);
+
+    let paragraph = Paragraph::new(help_text)
+        .scroll((*scroll as u16, 0))
+        .style(Style::default().bg(theme.paper_bg).fg(theme.base_fg));
+    f.render_widget(paragraph, list_area);
+}
+
+/// Width of the small confirmation prompts (quit, recovery)
+const PROMPT_WIDTH: u16 = 48;
+
+/// Save / Discard / Cancel modal shown when closing a buffer or quitting with unsaved changes
+fn draw_quit_prompt(f: &mut Frame, app: &App, scope: CloseScope) {
+    let theme = &app.theme;
+    let name = buffer::display_name(app.file_path.as_deref());
+    let action = match scope {
+        CloseScope::Buffer => "close",
+        CloseScope::App => "quit",
+    };
+
+    let body = vec![
+        Line::from(format!("  {name} has unsaved changes.")),
+        Line::from(""),
         Line::from(vec![
-            Span::styled("  F1", Style::default().fg(theme.accent)),
-            Span::raw("      Toggle this help menu"),
+            Span::styled("  S", Style::default().fg(theme.accent)),
+            Span::raw(format!("      Save and {action}")),
         ]),
         Line::from(vec![
-            Span::styled("  F2", Style::default().fg(theme.accent)),
-            Span::raw("      Toggle focus mode (dim inactive lines)"),
+            Span::styled("  D", Style::default().fg(theme.accent)),
+            Span::raw(format!("      Discard changes and {action}")),
         ]),
         Line::from(vec![
-            Span::styled("  F3", Style::default().fg(theme.accent)),
-            Span::raw("      Toggle typewriter mode (center line)"),
+            Span::styled("  C/Esc", Style::default().fg(theme.accent)),
+            Span::raw("  Cancel and keep writing"),
         ]),
+    ];
+    draw_prompt(f, theme, "UNSAVED CHANGES", body);
+}
+
+/// Line ending / encoding / BOM choices for the current file
+fn draw_format_prompt(f: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let format = app.text_format;
+    let setting = |label: &str, value: String| {
         Line::from(vec![
-            Span::styled("  F4", Style::default().fg(theme.accent)),
-            Span::raw("      Toggle sound effects"),
+            Span::raw(format!("  {label:<14}")),
+            Span::styled(value, Style::default().fg(theme.accent)),
+        ])
+    };
+    let line_ending = match format.line_ending {
+        LineEnding::Lf => "LF (Unix, macOS)",
+        LineEnding::Crlf => "CRLF (Windows)",
+    };
+    let encoding = match format.encoding {
+        Encoding::Utf8 => "UTF-8",
+        Encoding::Windows1252 => "Windows-1252 / Latin-1",
+    };
+    let bom = if format.bom { "Yes" } else { "No" };
+
+    let body = vec![
+        setting("Line endings", line_ending.to_string()),
+        setting("Encoding", encoding.to_string()),
+        setting("BOM", bom.to_string()),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("  L", Style::default().fg(theme.accent)),
+            Span::raw("      Switch line endings"),
         ]),
         Line::from(vec![
-            Span::styled("  F5", Style::default().fg(theme.accent)),
-            Span::raw("      Cycle theme (Light/Dark/Retro)"),
+            Span::styled("  E", Style::default().fg(theme.accent)),
+            Span::raw("      Switch encoding"),
         ]),
         Line::from(vec![
-            Span::styled("  F6", Style::default().fg(theme.accent)),
-            Span::raw("      Toggle double spacing"),
+            Span::styled("  B", Style::default().fg(theme.accent)),
+            Span::raw("      Add or remove the UTF-8 byte order mark"),
         ]),
+        Line::from(vec![
+            Span::styled("  Esc", Style::default().fg(theme.accent)),
+            Span::raw("    Done; changes apply when you save"),
+        ]),
+    ];
+    draw_prompt(f, theme, "FILE FORMAT", body);
+}
+
+/// Reload / Overwrite modal shown when another program changed the open file
+fn draw_external_prompt(f: &mut Frame, app: &App) {
+    let theme = &app.theme;
+    let name = buffer::display_name(app.file_path.as_deref());
+    let mut body = vec![
+        Line::from(Span::styled(
+            format!("  {name}"),
+            Style::default().fg(theme.accent),
+        )),
+        Line::from("  was changed by another program since it"),
+        Line::from("  was opened or last saved here."),
+    ];
+    if app.has_unsaved_changes {
+        body.push(Line::from(Span::styled(
+            "  You also have unsaved changes.",
+            Style::default().fg(theme.status_bad),
+        )));
+    }
+    body.extend([
         Line::from(""),
         Line::from(vec![
-            Span::styled("  Ctrl+S", Style::default().fg(theme.accent)),
-            Span::raw("  Save file"),
+            Span::styled("  R", Style::default().fg(theme.accent)),
+            Span::raw(format!("      Reload it{}", undo_hint(&app.config.keymap))),
         ]),
         Line::from(vec![
-            Span::styled("  Ctrl+T", Style::default().fg(theme.accent)),
-            Span::raw("  Toggle typewriter mode"),
+            Span::styled("  O", Style::default().fg(theme.accent)),
+            Span::raw("      Overwrite it with this version"),
         ]),
         Line::from(vec![
-            Span::styled("  Esc", Style::default().fg(theme.accent)),
-            Span::raw("     Quit application"),
+            Span::styled("  D", Style::default().fg(theme.accent)),
+            Span::raw("      Show the differences"),
+        ]),
+        Line::from(vec![
+            Span::styled("  C/Esc", Style::default().fg(theme.accent)),
+            Span::raw("  Keep editing"),
+        ]),
+    ]);
+    draw_prompt(f, theme, "CHANGED ON DISK", body);
+}
+
+/// The changed-on-disk prompt with the buffer compared line by line against
+/// the file: `-` lines are only here, `+` lines only on disk
+fn draw_external_diff(f: &mut Frame, app: &App, diff: &[DiffLine], scroll: usize) {
+    let theme = &app.theme;
+    let size = f.size();
+    let modal_width = 80
+        .min(size.width.saturating_sub(4))
+        .max(PROMPT_WIDTH.min(size.width));
+    let modal_height = size.height.saturating_sub(4).max(6.min(size.height));
+    let modal_area = Rect {
+        x: (size.width.saturating_sub(modal_width)) / 2,
+        y: (size.height.saturating_sub(modal_height)) / 2,
+        width: modal_width,
+        height: modal_height,
+    };
+    let rows = modal_height.saturating_sub(4) as usize;
+
+    let mut lines: Vec<Line> = diff
+        .iter()
+        .skip(scroll)
+        .take(rows)
+        .map(|line| match line {
+            DiffLine::Same(text) => Line::from(Span::styled(
+                format!("  {text}"),
+                Style::default().fg(theme.dim_text),
+            )),
+            DiffLine::Removed(text) => Line::from(Span::styled(
+                format!("- {text}"),
+                Style::default().fg(theme.status_bad),
+            )),
+            DiffLine::Added(text) => Line::from(Span::styled(
+                format!("+ {text}"),
+                Style::default().fg(theme.status_ok),
+            )),
+        })
+        .collect();
+    lines.resize(rows, Line::from(""));
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        " -:This version +:On disk ↑↓:Scroll R:Reload O:Overwrite D:Hide Esc:Keep",
+        Style::default().fg(theme.guide_color),
+    )));
+
+    let name = buffer::display_name(app.file_path.as_deref());
+    let block = Block::default()
+        .title(format!(" {name} changed on disk "))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border))
+        .style(Style::default().bg(theme.paper_bg).fg(theme.base_fg));
+
+    f.render_widget(Clear, modal_area);
+    f.render_widget(Paragraph::new(lines).block(block), modal_area);
+}
+
+/// Restore / Discard modal shown at startup when a swap file survived a crash
+fn draw_recovery_prompt(f: &mut Frame, theme: &Theme, swap: &Path, keymap: &Keymap) {
+    let name = swap
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let body = vec![
+        Line::from("  A previous session ended with unsaved"),
+        Line::from("  changes. They were kept in"),
+        Line::from(Span::styled(
+            format!("  {name}"),
+            Style::default().fg(theme.accent),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::styled("  R", Style::default().fg(theme.accent)),
+            Span::raw(format!("      Restore them{}", undo_hint(keymap))),
+        ]),
+        Line::from(vec![
+            Span::styled("  D", Style::default().fg(theme.accent)),
+            Span::raw("      Discard them and delete the file"),
         ]),
     ];
+    draw_prompt(f, theme, "RECOVER UNSAVED WORK", body);
+}
+
+/// Centered modal with a title bar, drawn over everything else
+fn draw_prompt(f: &mut Frame, theme: &Theme, title: &str, body: Vec<Line<'static>>) {
+    let size = f.size();
+    let modal_height = body.len() as u16 + 4;
+    let modal_area = Rect {
+        x: (size.width.saturating_sub(PROMPT_WIDTH)) / 2,
+        y: (size.height.saturating_sub(modal_height)) / 2,
+        width: PROMPT_WIDTH.min(size.width),
+        height: modal_height.min(size.height),
+    };
+
+    let mut text = vec![
+        Line::from(vec![Span::styled(
+            format!("{title:^width$}", width = PROMPT_WIDTH as usize - 2),
+            Style::default()
+                .fg(theme.base_bg)
+                .bg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(""),
+    ];
+    text.extend(body);
 
-    // Render the modal with border
     let block = Block::default()
         .borders(Borders::ALL)
         .border_style(Style::default().fg(theme.border))
         .style(Style::default().bg(theme.paper_bg).fg(theme.base_fg));
 
+    f.render_widget(Clear, modal_area);
     f.render_widget(block, modal_area);
 
-    // Render text inside the modal
     let inner_area = Rect {
         x: modal_area.x + 1,
         y: modal_area.y + 1,
@@ -488,7 +1072,402 @@ fn draw_help_overlay(f: &mut Frame, theme: &crate::theme::Theme) {
     };
 
     let paragraph =
-        Paragraph::new(help_text).style(Style::default().bg(theme.paper_bg).fg(theme.base_fg));
+        Paragraph::new(text).style(Style::default().bg(theme.paper_bg).fg(theme.base_fg));
 
     f.render_widget(paragraph, inner_area);
 }
+
+/// Save As minibuffer, drawn just above the footer
+fn draw_save_as(f: &mut Frame, theme: &Theme, state: &SaveAsState) {
+    let size = f.size();
+    let label_style = Style::default().fg(theme.header_fg);
+
+    let mut lines = vec![Line::from(vec![
+        Span::styled(" Save as: ", label_style),
+        Span::styled(
+            format!("{}▏", state.input),
+            Style::default()
+                .fg(theme.accent)
+                .add_modifier(Modifier::BOLD),
+        ),
+    ])];
+    if state.confirm_overwrite {
+        lines.push(Line::from(Span::styled(
+            format!(
+                " {} already exists. Overwrite? (y/n)",
+                state.path().display()
+            ),
+            Style::default().fg(theme.status_bad),
+        )));
+    } else if let Some(error) = &state.error {
+        lines.push(Line::from(Span::styled(
+            format!(" {error}"),
+            Style::default().fg(theme.status_bad),
+        )));
+    } else if state.completions.len() > 1 {
+        lines.push(Line::from(Span::styled(
+            format!(" {}", state.completions.join("  ")),
+            Style::default().fg(theme.guide_color),
+        )));
+    } else {
+        lines.push(Line::from(Span::styled(
+            " Tab:Complete Enter:Save Esc:Cancel",
+            label_style,
+        )));
+    }
+
+    let height = lines.len() as u16 + 2;
+    // Sit on top of the footer row
+    let area = Rect {
+        x: 0,
+        y: size.height.saturating_sub(height + 1),
+        width: size.width,
+        height: height.min(size.height),
+    };
+    let block = Block::default()
+        .borders(Borders::TOP | Borders::BOTTOM)
+        .border_style(Style::default().fg(theme.border))
+        .style(Style::default().bg(theme.header_bg).fg(theme.header_fg));
+
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Fuzzy Open picker, centered over the page
+fn draw_file_picker(f: &mut Frame, theme: &Theme, state: &OpenState) {
+    let size = f.size();
+    let modal_width = 64.min(size.width);
+    let modal_height = 20.min(size.height);
+    let modal_area = Rect {
+        x: (size.width.saturating_sub(modal_width)) / 2,
+        y: (size.height.saturating_sub(modal_height)) / 2,
+        width: modal_width,
+        height: modal_height,
+    };
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled(" Open: ", Style::default().fg(theme.header_fg)),
+            Span::styled(
+                format!("{}▏", state.query),
+                Style::default()
+                    .fg(theme.accent)
+                    .add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        Line::from(""),
+    ];
+
+    // Rows left after the query, the gap and the status line
+    let list_rows = modal_height.saturating_sub(5) as usize;
+    let first = state.selected.saturating_sub(list_rows.saturating_sub(1));
+    for (pos, &file) in state.matches.iter().enumerate().skip(first).take(list_rows) {
+        let style = if pos == state.selected {
+            Style::default()
+                .bg(theme.selection)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!(" {}", state.files[file]),
+            style,
+        )));
+    }
+    while lines.len() < list_rows + 2 {
+        lines.push(Line::from(""));
+    }
+
+    lines.push(Line::from(Span::styled(
+        format!(
+            " {} of {} files  ↑↓:Select Enter:Open Esc:Cancel",
+            state.matches.len(),
+            state.files.len()
+        ),
+        Style::default().fg(theme.guide_color),
+    )));
+
+    let block = Block::default()
+        .title(" Open file ")
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border))
+        .style(Style::default().bg(theme.paper_bg).fg(theme.base_fg));
+
+    f.render_widget(Clear, modal_area);
+    f.render_widget(Paragraph::new(lines).block(block), modal_area);
+}
+
+/// Chapter list for a manuscript project, with per-chapter and total counts
+fn draw_navigator(
+    f: &mut Frame,
+    theme: &Theme,
+    project: &Project,
+    selected: usize,
+    current: Option<usize>,
+    lines_per_page: usize,
+) {
+    let size = f.size();
+    let modal_width = 56.min(size.width);
+    let list_rows = project
+        .chapters
+        .len()
+        .min(size.height.saturating_sub(8) as usize);
+    let modal_height = (list_rows as u16 + 6).min(size.height);
+    let modal_area = Rect {
+        x: (size.width.saturating_sub(modal_width)) / 2,
+        y: (size.height.saturating_sub(modal_height)) / 2,
+        width: modal_width,
+        height: modal_height,
+    };
+    let inner_width = modal_width.saturating_sub(2) as usize;
+
+    let mut lines = Vec::new();
+    let first = selected.saturating_sub(list_rows.saturating_sub(1));
+    for index in (first..project.chapters.len()).take(list_rows) {
+        let marker = if Some(index) == current { "▸" } else { " " };
+        let label = format!("{marker}{:>3}. {}", index + 1, project.chapter_name(index));
+        let words = format!("{} w ", project.stats[index].words);
+        let gap = inner_width.saturating_sub(label.chars().count() + words.len());
+        let style = if index == selected {
+            Style::default()
+                .bg(theme.selection)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!("{label}{}{words}", " ".repeat(gap)),
+            style,
+        )));
+    }
+
+    let total_words: usize = project.stats.iter().map(|s| s.words).sum();
+    let total_pages = project.pages_before(project.chapters.len(), lines_per_page);
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        format!(" Manuscript: {total_words} words, {total_pages} pages"),
+        Style::default().fg(theme.accent),
+    )));
+    lines.push(Line::from(Span::styled(
+        " ↑↓:Select Enter:Open C:Compile Esc:Close",
+        Style::default().fg(theme.guide_color),
+    )));
+
+    let title = project.title.as_deref().unwrap_or("Chapters");
+    let block = Block::default()
+        .title(format!(" {title} "))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.border))
+        .style(Style::default().bg(theme.paper_bg).fg(theme.base_fg));
+
+    f.render_widget(Clear, modal_area);
+    f.render_widget(Paragraph::new(lines).block(block), modal_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input;
+    use crate::test_util;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    /// Big enough for the help overlay
+    const WIDTH: u16 = 64;
+    const HEIGHT: u16 = 30;
+
+    /// A small page (narrow paper, four lines a page) so one screen shows
+    /// page breaks
+    fn scripted_app() -> App {
+        let mut app = test_util::app();
+        app.config.layout.text_width = 40;
+        app.config.typewriter.bell_column = 36;
+        app.config.typewriter.lines_per_page = 4;
+        app.config.typewriter.page_feed_pause_ms = 0;
+        app
+    }
+
+    /// Run `script` once in each theme, switching with F5 first, and compare
+    /// the screen with the `<name>_<theme>` snapshot
+    fn check_each_theme(name: &str, script: impl Fn(&mut App)) {
+        for presses in 0..3 {
+            let mut app = scripted_app();
+            for _ in 0..presses {
+                press(&mut app, KeyCode::F(5), KeyModifiers::NONE);
+            }
+            script(&mut app);
+            let theme = app.current_theme_type.name().to_lowercase();
+            assert_snapshot(&format!("{name}_{theme}"), &render(&mut app));
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+        input::handle_key(app, KeyEvent::new(code, modifiers));
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            let code = if c == '\n' {
+                KeyCode::Enter
+            } else {
+                KeyCode::Char(c)
+            };
+            press(app, code, KeyModifiers::NONE);
+        }
+    }
+
+    fn render(app: &mut App) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+        terminal.draw(|f| draw(f, app)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    /// The screen as text, then the same grid with a letter for each
+    /// distinct style, then what each letter stands for
+    fn snapshot(buffer: &Buffer) -> String {
+        const KEYS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+        let mut styles: Vec<Style> = Vec::new();
+        let mut text = String::new();
+        let mut grid = String::new();
+        for y in 0..buffer.area.height {
+            for x in 0..buffer.area.width {
+                let cell = buffer.get(x, y);
+                text.push_str(cell.symbol());
+                let style = cell.style();
+                let idx = styles.iter().position(|&s| s == style).unwrap_or_else(|| {
+                    styles.push(style);
+                    styles.len() - 1
+                });
+                grid.push(KEYS.chars().nth(idx).expect("too many styles"));
+            }
+            text.push('\n');
+            grid.push('\n');
+        }
+        let legend: String = styles
+            .iter()
+            .zip(KEYS.chars())
+            .map(|(style, key)| {
+                format!(
+                    "{key}: fg={:?} bg={:?} mod={:?}\n",
+                    style.fg.unwrap_or_default(),
+                    style.bg.unwrap_or_default(),
+                    style.add_modifier
+                )
+            })
+            .collect();
+        format!("{text}\n{grid}\n{legend}")
+    }
+
+    /// Compare with `src/snapshots/<name>.snap`. Run the tests with
+    /// `UPDATE_SNAPSHOTS=1` to write the snapshots after a deliberate change,
+    /// then review the diff.
+    fn assert_snapshot(name: &str, buffer: &Buffer) {
+        let actual = snapshot(buffer);
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/snapshots")
+            .join(format!("{name}.snap"));
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &actual).unwrap();
+            return;
+        }
+        // A checkout with autocrlf may have given the file CRLF endings
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_default()
+            .replace("\r\n", "\n");
+        assert!(
+            expected == actual,
+            "{name} no longer matches {}; if the change is intended, rerun with \
+             UPDATE_SNAPSHOTS=1\n{actual}",
+            path.display()
+        );
+    }
+
+    #[test]
+    fn test_snapshot_pages_and_margin_guide() {
+        check_each_theme("pages", |app| {
+            // Typewriter mode off, so the top of the page stays in view
+            press(app, KeyCode::F(3), KeyModifiers::NONE);
+            type_text(
+                app,
+                "# Storm\n\nThe keeper had *never* seen\none like it, and when morning\n\
+                 came **nothing** was where\nshe left it.\n\n- gulls\n- the `pier`",
+            );
+        });
+    }
+
+    #[test]
+    fn test_snapshot_focus_and_selection() {
+        check_each_theme("focus", |app| {
+            type_text(app, "First line\nSecond line\nThird line");
+            press(app, KeyCode::F(2), KeyModifiers::NONE);
+            press(app, KeyCode::Up, KeyModifiers::NONE);
+            press(
+                app,
+                KeyCode::Left,
+                KeyModifiers::SHIFT | KeyModifiers::CONTROL,
+            );
+        });
+    }
+
+    #[test]
+    fn test_snapshot_help_overlay() {
+        check_each_theme("help", |app| {
+            type_text(app, "Some words under the help");
+            press(app, KeyCode::F(1), KeyModifiers::NONE);
+        });
+    }
+
+    #[test]
+    fn test_help_follows_the_keymap_and_scrolls() {
+        let mut app = scripted_app();
+        app.config.keymap = Keymap::with_overrides(&BTreeMap::from([
+            ("f2".to_string(), "none".to_string()),
+            ("alt+f".to_string(), "toggle_focus".to_string()),
+        ]));
+
+        press(&mut app, KeyCode::F(2), KeyModifiers::NONE);
+        assert!(!app.focus_mode);
+        press(&mut app, KeyCode::Char('f'), KeyModifiers::ALT);
+        assert!(app.focus_mode);
+
+        press(&mut app, KeyCode::F(1), KeyModifiers::NONE);
+        let screen = snapshot(&render(&mut app));
+        assert!(screen.contains("Alt+F"));
+        assert!(!screen.contains("F2 "));
+        assert!(!screen.contains("Quit (asks first"));
+
+        // Scrolling stops once the last entry is in view
+        for _ in 0..40 {
+            press(&mut app, KeyCode::Down, KeyModifiers::NONE);
+        }
+        let screen = snapshot(&render(&mut app));
+        assert!(screen.contains("Quit (asks first"));
+        assert!(app.help_scroll < 40);
+        assert_eq!(app.doc.cursor, 0);
+    }
+
+    #[test]
+    fn test_unbound_chords_type_nothing() {
+        let mut app = scripted_app();
+        app.config.keymap = Keymap::with_overrides(&BTreeMap::from([
+            ("ctrl+s".to_string(), "none".to_string()),
+            ("alt+r".to_string(), "none".to_string()),
+        ]));
+        let before = app.doc.content.to_string();
+
+        press(&mut app, KeyCode::Char('s'), KeyModifiers::CONTROL);
+        press(&mut app, KeyCode::Char('r'), KeyModifiers::ALT);
+        assert_eq!(app.doc.content.to_string(), before);
+
+        // AltGr still types
+        press(
+            &mut app,
+            KeyCode::Char('@'),
+            KeyModifiers::CONTROL | KeyModifiers::ALT,
+        );
+        assert!(app.doc.content.to_string().contains('@'));
+    }
+}